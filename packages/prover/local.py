@@ -12,6 +12,11 @@ import logging
 app = Flask(__name__)
 
 
+@app.route("/health", methods=["GET"])
+def health():
+    return jsonify({"status": "ok"})
+
+
 @app.route("/prove/email_auth", methods=["POST"])
 def prove_email_auth():
     logger = logging.getLogger(__name__)
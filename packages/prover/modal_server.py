@@ -44,6 +44,10 @@ def flask_app():
     print(handler)
     setup_logging(handler)
 
+    @app.get("/health")
+    def health():
+        return jsonify({"status": "ok"})
+
     @app.post("/prove/email_auth")
     def prove_email_auth():
         print("prove_email_auth")
@@ -10,15 +10,15 @@ fn main() {
     .unwrap()
     .write_to_file("./src/abis/email_auth.rs")
     .unwrap();
-    // Abigen::new(
-    //     "ECDSAOwnedDKIMRegistry",
-    //     "../contracts/artifacts/ECDSAOwnedDKIMRegistry.sol/ECDSAOwnedDKIMRegistry.json",
-    // )
-    // .unwrap()
-    // .generate()
-    // .unwrap()
-    // .write_to_file("./src/abis/ecdsa_owned_dkim_registry.rs")
-    // .unwrap();
+    Abigen::new(
+        "ECDSAOwnedDKIMRegistry",
+        "../contracts/artifacts/ECDSAOwnedDKIMRegistry.sol/ECDSAOwnedDKIMRegistry.json",
+    )
+    .unwrap()
+    .generate()
+    .unwrap()
+    .write_to_file("./src/abis/ecdsa_owned_dkim_registry.rs")
+    .unwrap();
     Abigen::new(
         "UserOverridableDKIMRegistry",
         "../contracts/artifacts/UserOverrideableDKIMRegistry.sol/UserOverrideableDKIMRegistry.json",
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS on_chain_transactions;
+
+-- PostgreSQL cannot drop a single enum value, so 'Transaction failed' is kept in status_enum.
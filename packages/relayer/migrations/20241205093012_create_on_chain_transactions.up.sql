@@ -0,0 +1,12 @@
+ALTER TYPE status_enum ADD VALUE IF NOT EXISTS 'Transaction failed';
+
+CREATE TABLE IF NOT EXISTS on_chain_transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    request_id UUID NOT NULL,
+    tx_hash TEXT,
+    receipt JSONB,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_on_chain_transactions_request_id ON on_chain_transactions(request_id);
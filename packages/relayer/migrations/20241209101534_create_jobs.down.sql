@@ -0,0 +1,8 @@
+DROP TABLE IF EXISTS jobs;
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_type WHERE typname = 'job_status_enum') THEN
+        DROP TYPE job_status_enum;
+    END IF;
+END $$;
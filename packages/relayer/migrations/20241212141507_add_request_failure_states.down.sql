@@ -0,0 +1,4 @@
+ALTER TABLE requests DROP COLUMN IF EXISTS error_message;
+ALTER TABLE requests DROP COLUMN IF EXISTS error_code;
+
+-- PostgreSQL cannot drop a single enum value, so 'Failed', 'Expired' and 'Rejected' are kept in status_enum.
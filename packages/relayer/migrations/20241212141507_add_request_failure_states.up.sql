@@ -0,0 +1,6 @@
+ALTER TYPE status_enum ADD VALUE IF NOT EXISTS 'Failed';
+ALTER TYPE status_enum ADD VALUE IF NOT EXISTS 'Expired';
+ALTER TYPE status_enum ADD VALUE IF NOT EXISTS 'Rejected';
+
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS error_code TEXT;
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS error_message TEXT;
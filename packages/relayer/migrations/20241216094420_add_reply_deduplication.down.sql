@@ -0,0 +1,8 @@
+ALTER TABLE expected_replies DROP COLUMN IF EXISTS reply_message_id;
+
+DROP INDEX IF EXISTS idx_email_auth_messages_email_nullifier;
+
+ALTER TABLE email_auth_messages DROP COLUMN IF EXISTS created_at;
+ALTER TABLE email_auth_messages DROP COLUMN IF EXISTS encoded_msg;
+ALTER TABLE email_auth_messages DROP COLUMN IF EXISTS email_nullifier;
+ALTER TABLE email_auth_messages DROP COLUMN IF EXISTS message_id;
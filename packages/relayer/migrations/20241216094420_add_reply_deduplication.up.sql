@@ -0,0 +1,8 @@
+ALTER TABLE email_auth_messages ADD COLUMN IF NOT EXISTS message_id TEXT;
+ALTER TABLE email_auth_messages ADD COLUMN IF NOT EXISTS email_nullifier TEXT;
+ALTER TABLE email_auth_messages ADD COLUMN IF NOT EXISTS encoded_msg BYTEA;
+ALTER TABLE email_auth_messages ADD COLUMN IF NOT EXISTS created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW();
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_email_auth_messages_email_nullifier ON email_auth_messages(email_nullifier);
+
+ALTER TABLE expected_replies ADD COLUMN IF NOT EXISTS reply_message_id VARCHAR(255);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS idx_requests_expires_at;
+
+ALTER TABLE requests DROP COLUMN IF EXISTS reminder_sent_at;
+ALTER TABLE requests DROP COLUMN IF EXISTS expires_at;
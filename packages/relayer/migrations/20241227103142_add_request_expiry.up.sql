@@ -0,0 +1,4 @@
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS expires_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS reminder_sent_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_requests_expires_at ON requests(expires_at) WHERE status = 'Email sent';
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    request_id UUID NOT NULL,
+    job_id UUID NOT NULL,
+    url TEXT NOT NULL,
+    status status_enum NOT NULL,
+    attempt INTEGER NOT NULL,
+    response_status INTEGER,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_deliveries_request_id ON webhook_deliveries(request_id);
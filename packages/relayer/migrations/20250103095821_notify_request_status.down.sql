@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS requests_status_notify ON requests;
+DROP FUNCTION IF EXISTS notify_request_status();
@@ -0,0 +1,18 @@
+CREATE OR REPLACE FUNCTION notify_request_status() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'UPDATE' AND OLD.status = NEW.status THEN
+        RETURN NEW;
+    END IF;
+    PERFORM pg_notify(
+        'request_status',
+        json_build_object('id', NEW.id, 'status', NEW.status, 'errorCode', NEW.error_code)::text
+    );
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS requests_status_notify ON requests;
+CREATE TRIGGER requests_status_notify
+    AFTER INSERT OR UPDATE OF status ON requests
+    FOR EACH ROW
+    EXECUTE FUNCTION notify_request_status();
@@ -0,0 +1,6 @@
+DROP INDEX IF EXISTS idx_requests_group_id;
+
+ALTER TABLE requests DROP COLUMN IF EXISTS created_at;
+ALTER TABLE requests DROP COLUMN IF EXISTS group_id;
+
+DROP TABLE IF EXISTS request_groups;
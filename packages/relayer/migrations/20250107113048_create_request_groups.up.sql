@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS request_groups (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    threshold INTEGER NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS group_id UUID REFERENCES request_groups(id);
+
+-- clock_timestamp() rather than NOW(), so requests created in one transaction keep their order
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS created_at TIMESTAMP WITH TIME ZONE DEFAULT clock_timestamp();
+
+CREATE INDEX IF NOT EXISTS idx_requests_group_id ON requests(group_id);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS imap_processed_messages (
+    mailbox TEXT NOT NULL,
+    uid_validity BIGINT NOT NULL,
+    uid BIGINT NOT NULL,
+    processed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (mailbox, uid_validity, uid)
+);
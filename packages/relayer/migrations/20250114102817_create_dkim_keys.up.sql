@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS dkim_keys (
+    chain TEXT NOT NULL,
+    registry_address TEXT NOT NULL,
+    domain TEXT NOT NULL,
+    selector TEXT NOT NULL,
+    public_key_hash TEXT NOT NULL,
+    registered BOOLEAN NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    checked_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (chain, registry_address, domain, selector, public_key_hash)
+);
@@ -0,0 +1,5 @@
+ALTER TABLE dkim_keys DROP COLUMN IF EXISTS revocation_tx_hash;
+ALTER TABLE dkim_keys DROP COLUMN IF EXISTS revoked_at;
+ALTER TABLE dkim_keys DROP COLUMN IF EXISTS empty_key_checks;
+ALTER TABLE dkim_keys DROP COLUMN IF EXISTS revocation_reason;
+ALTER TABLE dkim_keys DROP COLUMN IF EXISTS flagged_at;
@@ -0,0 +1,5 @@
+ALTER TABLE dkim_keys ADD COLUMN IF NOT EXISTS flagged_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE dkim_keys ADD COLUMN IF NOT EXISTS revocation_reason TEXT;
+ALTER TABLE dkim_keys ADD COLUMN IF NOT EXISTS empty_key_checks INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE dkim_keys ADD COLUMN IF NOT EXISTS revoked_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE dkim_keys ADD COLUMN IF NOT EXISTS revocation_tx_hash TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE request_groups DROP COLUMN IF EXISTS client_id;
+ALTER TABLE requests DROP COLUMN IF EXISTS client_id;
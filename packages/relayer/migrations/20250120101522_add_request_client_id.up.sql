@@ -0,0 +1,3 @@
+-- The API client that submitted a request or group, NULL if it was submitted without auth
+ALTER TABLE requests ADD COLUMN IF NOT EXISTS client_id UUID REFERENCES api_clients(id);
+ALTER TABLE request_groups ADD COLUMN IF NOT EXISTS client_id UUID REFERENCES api_clients(id);
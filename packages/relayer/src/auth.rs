@@ -0,0 +1,315 @@
+//! Authentication of API clients, the IMAP service and administrators.
+
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    middleware::Next,
+    response::Response,
+    Json,
+};
+use ethers::core::rand::{thread_rng, RngCore};
+use ethers::utils::hex;
+use hmac::{Hmac, Mac};
+use relayer_utils::LOG;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use slog::error;
+use uuid::Uuid;
+
+use crate::{
+    model::{get_api_client, get_api_client_by_key_hash, ApiClientModel},
+    RelayerState,
+};
+
+/// The header carrying the HMAC-SHA256 signature of a forwarded email.
+pub const SIGNATURE_HEADER: &str = "X-Relayer-Signature";
+
+/// The maximum size of a forwarded email read for signature verification.
+const MAX_EMAIL_BYTES: usize = 2 * 1024 * 1024;
+
+/// The number of seconds a status stream token is valid for.
+pub const STREAM_TOKEN_TTL_SECS: i64 = 300;
+
+/// The query parameters of the status stream.
+#[derive(Debug, Deserialize)]
+pub struct StreamTokenQuery {
+    /// A token issued by `/api/status/:id/streamToken`.
+    pub token: Option<String>,
+}
+
+/// Generates a new random API key.
+///
+/// # Returns
+///
+/// The API key as a hex string.
+pub fn generate_api_key() -> String {
+    let mut key = [0u8; 32];
+    thread_rng().fill_bytes(&mut key);
+    hex::encode(key)
+}
+
+/// Hashes an API key for storage, so leaked rows do not reveal usable keys.
+///
+/// # Arguments
+///
+/// * `api_key` - The API key to hash.
+///
+/// # Returns
+///
+/// The SHA-256 hash of the key as a hex string.
+pub fn hash_api_key(api_key: &str) -> String {
+    hex::encode(Sha256::digest(api_key.as_bytes()))
+}
+
+/// Signs a payload with HMAC-SHA256.
+///
+/// # Arguments
+///
+/// * `secret` - The shared secret.
+/// * `payload` - The payload to sign.
+///
+/// # Returns
+///
+/// The signature as a hex string prefixed with `sha256=`.
+pub fn sign_payload(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(payload);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Verifies the HMAC-SHA256 signature of a payload.
+///
+/// # Arguments
+///
+/// * `secret` - The shared secret.
+/// * `payload` - The signed payload.
+/// * `signature` - The hex signature, optionally prefixed with `sha256=`.
+///
+/// # Returns
+///
+/// `true` if the signature is valid.
+pub fn verify_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let signature = signature.trim().trim_start_matches("sha256=");
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(payload);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Middleware requiring a valid API key as a bearer token.
+///
+/// The authenticated `ApiClientModel` is added to the request extensions.
+pub async fn require_api_key(
+    State(relayer_state): State<Arc<RelayerState>>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    if !relayer_state.config.auth.enabled {
+        return Ok(next.run(request).await);
+    }
+
+    let api_key = bearer_token(request.headers()).ok_or_else(|| unauthorized("Missing API key"))?;
+    let api_client = get_api_client_by_key_hash(&relayer_state.db, &hash_api_key(api_key))
+        .await
+        .map_err(|e| {
+            error!(LOG, "Failed to look up API key: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+        })?
+        .ok_or_else(|| unauthorized("Invalid API key"))?;
+
+    request.extensions_mut().insert(api_client);
+    Ok(next.run(request).await)
+}
+
+/// Issues a token authorizing the status stream of a request.
+///
+/// Browsers' `EventSource` cannot send an `Authorization` header, so the stream also accepts this
+/// short-lived token as the `token` query parameter. The token is signed with the server's
+/// `auth.streamTokenSecret` and names the client it was issued to, so it stops working once that
+/// client is revoked.
+///
+/// # Arguments
+///
+/// * `secret` - The secret stream tokens are signed with.
+/// * `api_client` - The authenticated API client.
+/// * `request_id` - The unique identifier of the request to stream.
+///
+/// # Returns
+///
+/// The token and the Unix timestamp it expires at.
+pub fn issue_stream_token(
+    secret: &str,
+    api_client: &ApiClientModel,
+    request_id: Uuid,
+) -> (String, i64) {
+    let expires_at = chrono::Utc::now().timestamp() + STREAM_TOKEN_TTL_SECS;
+    let signature = sign_payload(
+        secret,
+        stream_token_payload(api_client.id, request_id, expires_at).as_bytes(),
+    );
+    let token = format!(
+        "{}.{}.{}",
+        api_client.id,
+        expires_at,
+        signature.trim_start_matches("sha256=")
+    );
+    (token, expires_at)
+}
+
+/// Middleware requiring a valid API key, or a stream token for the streamed request.
+///
+/// The client the token was issued to is added to the request extensions, like the client of an
+/// API key.
+pub async fn require_api_key_or_stream_token(
+    State(relayer_state): State<Arc<RelayerState>>,
+    Path(request_id): Path<Uuid>,
+    Query(query): Query<StreamTokenQuery>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let Some(token) = query.token else {
+        return require_api_key(State(relayer_state), request, next).await;
+    };
+    if !relayer_state.config.auth.enabled {
+        return Ok(next.run(request).await);
+    }
+
+    let mut parts = token.splitn(3, '.');
+    let (Some(client_id), Some(expires_at), Some(signature)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(unauthorized("Invalid token"));
+    };
+    let (Ok(client_id), Ok(expires_at)) = (client_id.parse::<Uuid>(), expires_at.parse::<i64>())
+    else {
+        return Err(unauthorized("Invalid token"));
+    };
+    if expires_at < chrono::Utc::now().timestamp() {
+        return Err(unauthorized("Token expired"));
+    }
+
+    let secret = relayer_state
+        .config
+        .auth
+        .stream_token_secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+        .ok_or_else(|| unauthorized("Stream tokens are not configured"))?;
+    let payload = stream_token_payload(client_id, request_id, expires_at);
+    if !verify_signature(secret, payload.as_bytes(), signature) {
+        return Err(unauthorized("Invalid token"));
+    }
+
+    let api_client = get_api_client(&relayer_state.db, client_id)
+        .await
+        .map_err(|e| {
+            error!(LOG, "Failed to look up API client: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": e.to_string()})),
+            )
+        })?
+        .ok_or_else(|| unauthorized("Invalid token"))?;
+
+    request.extensions_mut().insert(api_client);
+    Ok(next.run(request).await)
+}
+
+/// Builds the payload signed by a stream token.
+fn stream_token_payload(client_id: Uuid, request_id: Uuid, expires_at: i64) -> String {
+    format!("{}.{}.{}", client_id, request_id, expires_at)
+}
+
+/// Middleware requiring a valid signature of the forwarded email from the IMAP service.
+pub async fn require_signature(
+    State(relayer_state): State<Arc<RelayerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let auth_config = &relayer_state.config.auth;
+    if !auth_config.enabled {
+        return Ok(next.run(request).await);
+    }
+    let secret = auth_config
+        .receive_email_secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+        .ok_or_else(|| unauthorized("Email forwarding is not configured"))?;
+
+    let (parts, body) = request.into_parts();
+    let payload = axum::body::to_bytes(body, MAX_EMAIL_BYTES)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    let signature = parts
+        .headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| unauthorized("Missing signature"))?;
+    if !verify_signature(secret, &payload, signature) {
+        return Err(unauthorized("Invalid signature"));
+    }
+
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(payload)))
+        .await)
+}
+
+/// Middleware requiring the admin token as a bearer token.
+pub async fn require_admin_token(
+    State(relayer_state): State<Arc<RelayerState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let admin_token = relayer_state
+        .config
+        .auth
+        .admin_token
+        .as_deref()
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| {
+            (
+                StatusCode::FORBIDDEN,
+                Json(json!({"error": "Admin API is disabled"})),
+            )
+        })?;
+
+    // Compare digests rather than the tokens themselves to avoid leaking the token length
+    let token = bearer_token(request.headers()).ok_or_else(|| unauthorized("Missing token"))?;
+    if hash_api_key(token) != hash_api_key(admin_token) {
+        return Err(unauthorized("Invalid token"));
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Extracts the bearer token from the `Authorization` header.
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Builds an unauthorized error response.
+fn unauthorized(message: &str) -> (StatusCode, Json<Value>) {
+    (StatusCode::UNAUTHORIZED, Json(json!({"error": message})))
+}
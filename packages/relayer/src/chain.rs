@@ -1,10 +1,12 @@
 use crate::*;
-use abis::UserOverridableDKIMRegistry;
+use abis::{ECDSAOwnedDKIMRegistry, EmailAuth, EmailAuthMsg, UserOverridableDKIMRegistry};
 use anyhow::anyhow;
 use config::ChainConfig;
+use ethers::abi::{AbiParser, Function, LenientTokenizer, Token, Tokenizable, Tokenizer};
 use ethers::prelude::*;
 use ethers::signers::Signer;
 use ethers::utils::hex;
+use schema::{DkimRegistryType, OnChainTxSchema};
 use statics::SHARED_MUTEX;
 use std::collections::HashMap;
 
@@ -14,6 +16,15 @@ const CONFIRMATIONS: usize = 1;
 // Type alias for a SignerMiddleware that combines a provider and a local wallet
 type SignerM = SignerMiddleware<Provider<Http>, LocalWallet>;
 
+/// A DKIM registry contract of one of the supported types.
+#[derive(Debug, Clone)]
+pub enum DkimRegistry {
+    /// A `UserOverrideableDKIMRegistry`.
+    UserOverridable(UserOverridableDKIMRegistry<SignerM>),
+    /// An `ECDSAOwnedDKIMRegistry`.
+    EcdsaOwned(ECDSAOwnedDKIMRegistry<SignerM>),
+}
+
 /// Represents a client for interacting with the blockchain.
 #[derive(Debug, Clone)]
 pub struct ChainClient {
@@ -43,6 +54,61 @@ impl ChainClient {
         Ok(Self { client })
     }
 
+    /// Gets the DKIM registry at an address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the registry.
+    /// * `registry_type` - The type of the registry. Detected from the contract if `None`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `DkimRegistry`.
+    pub async fn dkim_registry(
+        &self,
+        address: Address,
+        registry_type: Option<DkimRegistryType>,
+    ) -> Result<DkimRegistry> {
+        let registry_type = match registry_type {
+            Some(registry_type) => registry_type,
+            None => self.detect_dkim_registry_type(address).await?,
+        };
+
+        Ok(match registry_type {
+            DkimRegistryType::UserOverridable => DkimRegistry::UserOverridable(
+                UserOverridableDKIMRegistry::new(address, self.client.clone()),
+            ),
+            DkimRegistryType::EcdsaOwned => {
+                DkimRegistry::EcdsaOwned(ECDSAOwnedDKIMRegistry::new(address, self.client.clone()))
+            }
+        })
+    }
+
+    /// Detects the type of a DKIM registry by the functions it exposes.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address of the registry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `DkimRegistryType`.
+    async fn detect_dkim_registry_type(&self, address: Address) -> Result<DkimRegistryType> {
+        // Only UserOverrideableDKIMRegistry has a main authorizer
+        let user_overridable = UserOverridableDKIMRegistry::new(address, self.client.clone());
+        if user_overridable.main_authorizer().call().await.is_ok() {
+            return Ok(DkimRegistryType::UserOverridable);
+        }
+
+        // Only ECDSAOwnedDKIMRegistry has a signer
+        let ecdsa_owned = ECDSAOwnedDKIMRegistry::new(address, self.client.clone());
+        if ecdsa_owned.signer().call().await.is_ok() {
+            return Ok(DkimRegistryType::EcdsaOwned);
+        }
+
+        Err(anyhow!("Unsupported DKIM registry at {:?}", address))
+    }
+
     /// Sets the DKIM public key hash.
     ///
     /// # Arguments
@@ -51,30 +117,94 @@ impl ChainClient {
     /// * `domain_name` - The domain name.
     /// * `public_key_hash` - The public key hash as a 32-byte array.
     /// * `signature` - The signature as Bytes.
-    /// * `dkim` - The ECDSA Owned DKIM Registry.
+    /// * `dkim` - The DKIM registry.
     ///
     /// # Returns
     ///
     /// A `Result` containing the transaction hash as a String if successful, or an error if not.
     pub async fn set_dkim_public_key_hash(
         &self,
+        selector: String,
         domain_name: String,
         public_key_hash: [u8; 32],
         signature: Bytes,
-        dkim: UserOverridableDKIMRegistry<SignerM>,
+        dkim: &DkimRegistry,
     ) -> Result<String> {
         // Mutex is used to prevent nonce conflicts.
         let mut mutex = SHARED_MUTEX.lock().await;
         *mutex += 1;
 
         // Call the contract method
-        let main_authorizer = dkim.main_authorizer().call().await?;
-        let call =
-            dkim.set_dkim_public_key_hash(domain_name, public_key_hash, main_authorizer, signature);
-        let tx = call.send().await?;
+        let tx = match dkim {
+            DkimRegistry::UserOverridable(dkim) => {
+                let main_authorizer = dkim.main_authorizer().call().await?;
+                let call = dkim.set_dkim_public_key_hash(
+                    domain_name,
+                    public_key_hash,
+                    main_authorizer,
+                    signature,
+                );
+                call.send().await?.confirmations(CONFIRMATIONS).await?
+            }
+            DkimRegistry::EcdsaOwned(dkim) => {
+                let call = dkim.set_dkim_public_key_hash(
+                    selector,
+                    domain_name,
+                    public_key_hash,
+                    signature,
+                );
+                call.send().await?.confirmations(CONFIRMATIONS).await?
+            }
+        };
 
         // Wait for the transaction to be confirmed
-        let receipt = tx
+        let receipt = tx.ok_or(anyhow!("No receipt"))?;
+
+        // Format the transaction hash
+        let tx_hash = receipt.transaction_hash;
+        let tx_hash = format!("0x{}", hex::encode(tx_hash.as_bytes()));
+        Ok(tx_hash)
+    }
+
+    /// Revokes the DKIM public key hash.
+    ///
+    /// Only `ECDSAOwnedDKIMRegistry` can be revoked with a signature of the relayer.
+    ///
+    /// # Arguments
+    ///
+    /// * `selector` - The selector string.
+    /// * `domain_name` - The domain name.
+    /// * `public_key_hash` - The public key hash as a 32-byte array.
+    /// * `signature` - The signature of the revocation message as Bytes.
+    /// * `dkim` - The DKIM registry.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the transaction hash as a String if successful, or an error if not.
+    pub async fn revoke_dkim_public_key_hash(
+        &self,
+        selector: String,
+        domain_name: String,
+        public_key_hash: [u8; 32],
+        signature: Bytes,
+        dkim: &DkimRegistry,
+    ) -> Result<String> {
+        let DkimRegistry::EcdsaOwned(dkim) = dkim else {
+            return Err(anyhow!(
+                "Only ECDSAOwnedDKIMRegistry supports signed revocations"
+            ));
+        };
+
+        // Mutex is used to prevent nonce conflicts.
+        let mut mutex = SHARED_MUTEX.lock().await;
+        *mutex += 1;
+
+        // Call the contract method
+        let call =
+            dkim.revoke_dkim_public_key_hash(selector, domain_name, public_key_hash, signature);
+        let receipt = call
+            .send()
+            .await?
             .confirmations(CONFIRMATIONS)
             .await?
             .ok_or(anyhow!("No receipt"))?;
@@ -91,7 +221,7 @@ impl ChainClient {
     ///
     /// * `domain_name` - The domain name.
     /// * `public_key_hash` - The public key hash as a 32-byte array.
-    /// * `dkim` - The ECDSA Owned DKIM Registry.
+    /// * `dkim` - The DKIM registry.
     ///
     /// # Returns
     ///
@@ -100,14 +230,158 @@ impl ChainClient {
         &self,
         domain_name: ::std::string::String,
         public_key_hash: [u8; 32],
-        dkim: UserOverridableDKIMRegistry<SignerM>,
+        dkim: &DkimRegistry,
     ) -> Result<bool> {
         // Call the contract method to check if the hash is valid
-        let main_authorizer = dkim.main_authorizer().call().await?;
-        let is_set = dkim
-            .dkim_public_key_hashes(domain_name, public_key_hash, main_authorizer)
-            .call()
-            .await?;
+        let is_set = match dkim {
+            DkimRegistry::UserOverridable(dkim) => {
+                let main_authorizer = dkim.main_authorizer().call().await?;
+                dkim.dkim_public_key_hashes(domain_name, public_key_hash, main_authorizer)
+                    .call()
+                    .await?
+            }
+            DkimRegistry::EcdsaOwned(dkim) => {
+                dkim.is_dkim_public_key_hash_valid(domain_name, public_key_hash)
+                    .call()
+                    .await?
+            }
+        };
         Ok(is_set)
     }
+
+    /// Sends an email auth message on-chain as described by the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `on_chain` - The target contract and calldata shape of the transaction.
+    /// * `email_auth_msg` - The email auth message to submit.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the hash of the sent transaction.
+    pub async fn send_email_auth_msg(
+        &self,
+        on_chain: &OnChainTxSchema,
+        email_auth_msg: EmailAuthMsg,
+    ) -> Result<H256> {
+        // Mutex is used to prevent nonce conflicts.
+        let mut mutex = SHARED_MUTEX.lock().await;
+        *mutex += 1;
+
+        let tx_hash = match &on_chain.function_signature {
+            None => {
+                let email_auth = EmailAuth::new(on_chain.target_address, self.client.clone());
+                let call = email_auth.auth_email(email_auth_msg);
+                let tx = call.send().await?;
+                tx.tx_hash()
+            }
+            Some(signature) => {
+                let calldata = encode_controller_call(signature, on_chain, email_auth_msg)?;
+                let tx = TransactionRequest::new()
+                    .to(on_chain.target_address)
+                    .data(calldata);
+                let tx = self.client.send_transaction(tx, None).await?;
+                tx.tx_hash()
+            }
+        };
+
+        Ok(tx_hash)
+    }
+
+    /// Waits for a sent transaction to be confirmed.
+    ///
+    /// # Arguments
+    ///
+    /// * `tx_hash` - The hash of the transaction.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the receipt of the confirmed transaction, or `None` if the
+    /// transaction was dropped from the mempool.
+    pub async fn wait_for_transaction(&self, tx_hash: H256) -> Result<Option<TransactionReceipt>> {
+        let receipt = PendingTransaction::new(tx_hash, self.client.provider())
+            .confirmations(CONFIRMATIONS)
+            .await?;
+        Ok(receipt)
+    }
+}
+
+/// Parses the signature of a controller function.
+///
+/// # Arguments
+///
+/// * `signature` - The function signature, with or without the leading `function` keyword.
+///
+/// # Returns
+///
+/// A `Result` containing the parsed `Function`.
+pub fn parse_controller_function(signature: &str) -> Result<Function> {
+    let signature = signature.trim();
+    let signature = if signature.starts_with("function ") {
+        signature.to_string()
+    } else {
+        format!("function {}", signature)
+    };
+    AbiParser::default()
+        .parse_function(&signature)
+        .map_err(|e| anyhow!("Invalid function signature: {}", e))
+}
+
+/// Encodes the calldata of a controller function taking an email auth message.
+///
+/// # Arguments
+///
+/// * `signature` - The signature of the controller function.
+/// * `on_chain` - The position of the email auth message and the remaining arguments.
+/// * `email_auth_msg` - The email auth message to place at its position.
+///
+/// # Returns
+///
+/// A `Result` containing the encoded calldata.
+pub fn encode_controller_call(
+    signature: &str,
+    on_chain: &OnChainTxSchema,
+    email_auth_msg: EmailAuthMsg,
+) -> Result<Bytes> {
+    let function = parse_controller_function(signature)?;
+    if function.inputs.len() != on_chain.extra_args.len() + 1 {
+        return Err(anyhow!(
+            "Function {} expects {} arguments, but {} extra arguments were given",
+            function.name,
+            function.inputs.len(),
+            on_chain.extra_args.len()
+        ));
+    }
+    if on_chain.email_auth_msg_index >= function.inputs.len() {
+        return Err(anyhow!(
+            "Email auth message index {} is out of range",
+            on_chain.email_auth_msg_index
+        ));
+    }
+
+    // Place the email auth message at its index and tokenize the other arguments in order
+    let mut extra_args = on_chain.extra_args.iter();
+    let mut email_auth_msg = Some(email_auth_msg);
+    let tokens = function
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(idx, param)| {
+            if idx == on_chain.email_auth_msg_index {
+                Ok(email_auth_msg
+                    .take()
+                    .ok_or(anyhow!("Email auth message already used"))?
+                    .into_token())
+            } else {
+                let value = extra_args
+                    .next()
+                    .ok_or(anyhow!("Missing argument {}", param.name))?;
+                LenientTokenizer::tokenize(&param.kind, value)
+                    .map_err(|e| anyhow!("Invalid argument {}: {}", param.name, e))
+            }
+        })
+        .collect::<Result<Vec<Token>>>()?;
+
+    let calldata = function.encode_input(&tokens)?;
+    Ok(calldata.into())
 }
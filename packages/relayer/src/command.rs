@@ -1,8 +1,10 @@
 use anyhow::{anyhow, Result};
 use ethers::types::{Bytes, U256};
-use relayer_utils::{extract_template_vals_from_command, u256_to_bytes32_little};
+use relayer_utils::{
+    extract_template_vals_from_command, field_to_hex, u256_to_bytes32_little, ParsedEmail,
+};
 
-use crate::{constants::COMMAND_FIELDS, model::RequestModel};
+use crate::{constants::REPLY_SUBJECT_PREFIXES, model::RequestModel, schema::EmailTxAuthSchema};
 
 pub fn parse_command_template(template: &str, params: Vec<String>) -> String {
     let mut parsed_string = template.to_string();
@@ -20,16 +22,38 @@ pub fn parse_command_template(template: &str, params: Vec<String>) -> String {
     parsed_string
 }
 
+/// Returns the account code to include in the command email, if the request asks for it.
+///
+/// # Arguments
+///
+/// * `email_tx_auth` - The `EmailTxAuthSchema` of the request.
+///
+/// # Returns
+///
+/// The account code as a hex string without the `0x` prefix, or `None`.
+pub fn get_account_code_for_email(email_tx_auth: &EmailTxAuthSchema) -> Option<String> {
+    if !email_tx_auth.code_exists_in_email {
+        return None;
+    }
+    let hex_code = field_to_hex(&email_tx_auth.account_code.0);
+    Some(hex_code.trim_start_matches("0x").to_string())
+}
+
 /// Retrieves and encodes the command parameters for the email authentication request.
 ///
 /// # Arguments
 ///
 /// * `params` - The `EmailRequestContext` containing request details.
+/// * `legacy` - Whether the command is in the Subject header rather than the body.
 ///
 /// # Returns
 ///
 /// A `Result` containing a vector of encoded command parameters or an `EmailError`.
-pub async fn get_encoded_command_params(email: &str, request: RequestModel) -> Result<Vec<Bytes>> {
+pub async fn get_encoded_command_params(
+    email: &str,
+    request: RequestModel,
+    legacy: bool,
+) -> Result<Vec<Bytes>> {
     let command_template = request
         .email_tx_auth
         .command_template
@@ -37,10 +61,16 @@ pub async fn get_encoded_command_params(email: &str, request: RequestModel) -> R
         .map(String::from)
         .collect();
 
-    // Remove \r\n from email
-    let email = email.replace("=\r\n", "");
+    let command = if legacy {
+        ParsedEmail::new_from_raw_email(email)
+            .await?
+            .get_subject_all()?
+    } else {
+        // Remove \r\n from email
+        email.replace("=\r\n", "")
+    };
 
-    let command_params = extract_template_vals_from_command(&email, command_template)?;
+    let command_params = extract_template_vals_from_command(&command, command_template)?;
 
     let command_params_encoded = command_params
         .iter()
@@ -54,22 +84,51 @@ pub async fn get_encoded_command_params(email: &str, request: RequestModel) -> R
     Ok(command_params_encoded)
 }
 
+/// Returns the number of bytes preceding the command in the masked subject of a legacy reply.
+///
+/// Mail clients prefix the subject of a reply with e.g. `Re: `, which the `EmailAuth` contract
+/// has to skip before comparing the masked command with the command template.
+///
+/// # Arguments
+///
+/// * `masked_command` - The masked command from the public signals of a legacy proof.
+///
+/// # Returns
+///
+/// The number of bytes to skip.
+pub fn get_skipped_command_prefix(masked_command: &str) -> usize {
+    let mut command = masked_command.trim_start();
+    while let Some(prefix) = REPLY_SUBJECT_PREFIXES.iter().find(|prefix| {
+        command
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+    }) {
+        command = command[prefix.len()..].trim_start();
+    }
+    masked_command.len() - command.len()
+}
+
 /// Extracts the masked command from public signals.
 ///
 /// # Arguments
 ///
 /// * `public_signals` - The vector of public signals.
 /// * `start_idx` - The starting index for command extraction.
+/// * `command_fields` - The number of signals holding the command.
 ///
 /// # Returns
 ///
 /// A `Result` containing the masked command as a `String` or an error.
-pub fn get_masked_command(public_signals: Vec<U256>, start_idx: usize) -> Result<String> {
-    // Gather signals from start_idx to start_idx + COMMAND_FIELDS
+pub fn get_masked_command(
+    public_signals: Vec<U256>,
+    start_idx: usize,
+    command_fields: usize,
+) -> Result<String> {
+    // Gather signals from start_idx to start_idx + command_fields
     let command_bytes: Vec<u8> = public_signals
         .iter()
         .skip(start_idx)
-        .take(COMMAND_FIELDS)
+        .take(command_fields)
         .take_while(|&signal| *signal != U256::zero())
         .flat_map(u256_to_bytes32_little)
         .collect();
@@ -80,3 +139,20 @@ pub fn get_masked_command(public_signals: Vec<U256>, start_idx: usize) -> Result
 
     Ok(command)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_skipped_command_prefix_skips_reply_prefixes() {
+        assert_eq!(get_skipped_command_prefix("Send 0.1 ETH to "), 0);
+        assert_eq!(get_skipped_command_prefix("Re: Send 0.1 ETH to "), 4);
+        assert_eq!(get_skipped_command_prefix("RE: Fwd: Send 0.1 ETH to "), 9);
+    }
+
+    #[test]
+    fn get_skipped_command_prefix_keeps_commands_starting_like_prefixes() {
+        assert_eq!(get_skipped_command_prefix("Reply to alice"), 0);
+    }
+}
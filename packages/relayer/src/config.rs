@@ -2,10 +2,11 @@ use anyhow::Error;
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::env;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     /// The port number for the application to listen on.
@@ -14,8 +15,21 @@ pub struct Config {
     pub database_url: String,
     /// The URL for the SMTP server.
     pub smtp_url: String,
+    /// The transport used to send emails.
+    #[serde(default)]
+    pub email_transport: EmailTransportConfig,
     /// The URL for the prover service.
     pub prover_url: String,
+    /// The backend that generates proofs.
+    #[serde(default)]
+    pub prover: ProverConfig,
+    /// The prover services remote proofs are requested from.
+    #[serde(default)]
+    pub prover_pool: ProverPoolConfig,
+    /// The circuit profiles requests can be proven with. A request without a profile uses the
+    /// `default` profile, which falls back to the limits of the `email_auth` circuit if not listed.
+    #[serde(default)]
+    pub circuit_profiles: Vec<CircuitProfileConfig>,
     // /// The API key for Alchemy services.
     // pub alchemy_api_key: String,
     /// Configuration for file paths.
@@ -26,6 +40,33 @@ pub struct Config {
     pub chains: HashMap<String, ChainConfig>,
     /// Flag to enable JSON logging.
     pub json_logger: bool,
+    /// Flag to email the owner of a request when a reply from another address is rejected.
+    #[serde(default)]
+    pub notify_sender_mismatch: bool,
+    /// Configuration for the background job queue.
+    #[serde(default)]
+    pub queue: QueueConfig,
+    /// Configuration for API authentication.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Configuration for rate limiting of submitted requests.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Configuration for the expiry of requests awaiting a reply.
+    #[serde(default)]
+    pub expiry: ExpiryConfig,
+    /// Configuration for webhook callbacks on request status transitions.
+    #[serde(default)]
+    pub webhook: WebhookConfig,
+    /// Configuration for polling replies from an IMAP mailbox.
+    #[serde(default)]
+    pub imap: ImapConfig,
+    /// Configuration for receiving replies over SMTP or LMTP.
+    #[serde(default)]
+    pub lmtp: LmtpConfig,
+    /// Configuration for the cache and sync of registered DKIM keys.
+    #[serde(default)]
+    pub dkim: DkimConfig,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -48,7 +89,7 @@ pub struct IcpConfig {
     pub ic_replica_url: String,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ChainConfig {
     /// The private key for the blockchain.
@@ -57,10 +98,508 @@ pub struct ChainConfig {
     pub rpc_url: String,
     /// The chain ID for the blockchain.
     pub chain_id: u32,
+    /// The source of signed DKIM public keys registered on the chain.
+    #[serde(default)]
+    pub dkim_key_source: DkimKeySourceConfig,
     // /// The name used for Alchemy services.
     // pub alchemy_name: String,
 }
 
+#[derive(Deserialize, Clone, Default)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DkimKeySourceConfig {
+    /// Requests signed keys from the DKIM oracle canister configured in `icp`.
+    #[default]
+    Icp,
+    /// Looks keys up in DNS and signs them with a local key.
+    #[serde(rename_all = "camelCase")]
+    Dns {
+        /// The private key of the signer authorized by the DKIM registry.
+        signer_private_key: String,
+    },
+    /// Reads signed keys from a JSON file.
+    File {
+        /// The path to the file.
+        path: String,
+    },
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EmailTransportConfig {
+    /// Sends emails through the relayer-smtp service at `smtpUrl`.
+    #[default]
+    Http,
+    /// Sends emails directly to an SMTP server.
+    Smtp(SmtpConfig),
+    /// Writes emails to a directory instead of sending them.
+    File {
+        /// The directory the emails are written to.
+        dir: String,
+    },
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpConfig {
+    /// The hostname of the SMTP server.
+    pub host: String,
+    /// The port of the SMTP server.
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// The username to authenticate with. No authentication if unset.
+    pub username: Option<String>,
+    /// The password to authenticate with.
+    pub password: Option<String>,
+    /// How the connection to the SMTP server is secured.
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// The sender address, e.g. `Relayer <relayer@example.com>`.
+    pub from: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SmtpTls {
+    /// Upgrades a plain connection with STARTTLS.
+    #[default]
+    Starttls,
+    /// Connects over TLS.
+    Tls,
+    /// Does not encrypt the connection.
+    None,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ProverConfig {
+    /// Requests proofs from the prover service at `proverUrl`.
+    #[default]
+    Remote,
+    /// Generates proofs on this machine.
+    Local(LocalProverConfig),
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProverPoolConfig {
+    /// The prover services proofs are requested from. Defaults to `proverUrl` alone.
+    pub endpoints: Vec<ProverEndpointConfig>,
+    /// The number of seconds a prover service has to return a proof.
+    pub timeout_secs: u64,
+    /// The number of prover services a proof is requested from before proving fails.
+    pub max_attempts: usize,
+    /// The number of consecutive failures after which a prover service is only used if no other
+    /// is available, until it returns a proof again.
+    pub max_consecutive_failures: u32,
+    /// The number of seconds after its last failure before a failing prover service is tried
+    /// again ahead of the others.
+    pub failure_cooldown_secs: u64,
+    /// Flag to probe the prover services periodically.
+    pub health_check_enabled: bool,
+    /// The path of the health route of the prover services.
+    pub health_check_path: String,
+    /// The number of seconds between health probes.
+    pub health_check_interval_secs: u64,
+    /// The number of seconds a prover service has to answer a health probe.
+    pub health_check_timeout_secs: u64,
+}
+
+impl Default for ProverPoolConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            timeout_secs: 600,
+            max_attempts: 3,
+            max_consecutive_failures: 3,
+            failure_cooldown_secs: 300,
+            health_check_enabled: true,
+            health_check_path: "/health".to_string(),
+            health_check_interval_secs: 60,
+            health_check_timeout_secs: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverEndpointConfig {
+    /// The URL of the prover service.
+    pub url: String,
+    /// The share of proofs requested from the prover service, relative to the others. A prover
+    /// service with a weight of 0 is disabled.
+    #[serde(default = "default_prover_weight")]
+    pub weight: u32,
+}
+
+fn default_prover_weight() -> u32 {
+    1
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalProverConfig {
+    /// The directory holding the `<circuit>.zkey` and the witness generator of every circuit.
+    pub params_dir: String,
+    /// The directory the inputs, witnesses and proofs are written to while proving.
+    #[serde(default = "default_prover_build_dir")]
+    pub build_dir: String,
+    /// How witnesses are generated.
+    #[serde(default)]
+    pub witness_generator: WitnessGenerator,
+    /// The program that generates proofs from witnesses.
+    #[serde(default)]
+    pub backend: ProofBackend,
+    /// The path to the rapidsnark prover binary, e.g. `prover_cuda` for GPUs.
+    #[serde(default = "default_rapidsnark_path")]
+    pub rapidsnark_path: String,
+    /// The path to the snarkjs binary.
+    #[serde(default = "default_snarkjs_path")]
+    pub snarkjs_path: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WitnessGenerator {
+    /// Runs the C++ witness generator at `<paramsDir>/<circuit>_cpp/<circuit>`.
+    #[default]
+    Cpp,
+    /// Runs `<paramsDir>/<circuit>.wasm` with snarkjs.
+    Wasm,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ProofBackend {
+    /// Proves with rapidsnark.
+    #[default]
+    Rapidsnark,
+    /// Proves with snarkjs, which is much slower.
+    Snarkjs,
+}
+
+fn default_prover_build_dir() -> String {
+    "build".to_string()
+}
+
+fn default_rapidsnark_path() -> String {
+    "prover".to_string()
+}
+
+fn default_snarkjs_path() -> String {
+    "snarkjs".to_string()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CircuitProfileConfig {
+    /// The name requests select the profile by.
+    pub name: String,
+    /// The circuit the prover generates the proof with.
+    pub circuit: String,
+    /// The circuit used instead of `circuit` for requests asking for recipient-bound proofs.
+    pub recipient_circuit: String,
+    /// The maximum length in bytes of the canonicalized header.
+    pub max_header_length: usize,
+    /// The maximum length in bytes of the canonicalized body after the SHA precompute selector.
+    pub max_body_length: usize,
+    /// The number of public signals holding the domain name.
+    pub domain_fields: usize,
+    /// The number of public signals holding the masked command.
+    pub command_fields: usize,
+    /// Flag to skip the body hash check, for circuits that do not read the body.
+    pub ignore_body_hash_check: bool,
+    /// Flag for the legacy circuit of older deployments, which reads the command from the
+    /// Subject header instead of the body. `commandFields` then counts the masked subject.
+    pub legacy: bool,
+    /// The URL of the prover service for this profile, which then always proves remotely.
+    /// Defaults to the `prover` backend.
+    pub prover_url: Option<String>,
+}
+
+impl Default for CircuitProfileConfig {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            circuit: "email_auth".to_string(),
+            recipient_circuit: "email_auth_with_recipient".to_string(),
+            max_header_length: 1024,
+            max_body_length: 1024,
+            domain_fields: 9,
+            command_fields: 20,
+            ignore_body_hash_check: false,
+            legacy: false,
+            prover_url: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueueConfig {
+    /// The number of worker tasks processing jobs.
+    pub workers: usize,
+    /// The number of attempts after which a job is dead-lettered.
+    pub max_attempts: i32,
+    /// The delay in seconds before the first retry, doubled on every further attempt.
+    pub backoff_base_secs: u64,
+    /// The maximum delay in seconds between two attempts.
+    pub backoff_max_secs: u64,
+    /// The interval in milliseconds at which idle workers poll for new jobs.
+    pub poll_interval_ms: u64,
+    /// The number of seconds after which a running job is considered abandoned and claimed again.
+    pub lease_secs: u64,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            workers: 4,
+            max_attempts: 3,
+            backoff_base_secs: 30,
+            backoff_max_secs: 900,
+            poll_interval_ms: 1000,
+            lease_secs: 900,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuthConfig {
+    /// Flag to require API keys for client endpoints and signatures for forwarded emails.
+    pub enabled: bool,
+    /// The token authorizing requests to the admin endpoints. The admin API is disabled if unset or empty.
+    pub admin_token: Option<String>,
+    /// The secret shared with the IMAP service to sign the emails it forwards. Required if enabled.
+    pub receive_email_secret: Option<String>,
+    /// The secret status stream tokens are signed with. Required if enabled.
+    pub stream_token_secret: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimitConfig {
+    /// Flag to enable rate limiting of submitted requests.
+    pub enabled: bool,
+    /// The length in seconds of the window in which requests are counted.
+    pub window_secs: u64,
+    /// The maximum number of requests per API client in a window. Unlimited if unset.
+    pub per_client: Option<u32>,
+    /// The maximum number of requests per recipient email address in a window. Unlimited if unset.
+    pub per_recipient: Option<u32>,
+    /// The maximum number of requests per recipient domain in a window. Unlimited if unset, as
+    /// large providers are shared by many unrelated users.
+    pub per_domain: Option<u32>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_secs: 3600,
+            per_client: Some(1000),
+            per_recipient: Some(10),
+            per_domain: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExpiryConfig {
+    /// The number of seconds a request waits for a reply if the request sets no TTL.
+    pub default_ttl_secs: u64,
+    /// The number of seconds before expiry at which a reminder email is sent. No reminder if unset.
+    pub reminder_before_secs: Option<u64>,
+    /// The interval in seconds at which the scheduler looks for requests to remind or expire.
+    pub poll_interval_secs: u64,
+}
+
+impl Default for ExpiryConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl_secs: 3 * 24 * 60 * 60,
+            reminder_before_secs: Some(24 * 60 * 60),
+            poll_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WebhookConfig {
+    /// The URL status transitions are posted to for requests without a callback URL.
+    pub default_url: Option<String>,
+    /// The secret used to sign the callbacks. Callbacks are unsigned if unset.
+    pub secret: Option<String>,
+    /// The number of attempts after which a callback is given up.
+    pub max_attempts: i32,
+    /// The timeout in seconds of a single delivery attempt.
+    pub timeout_secs: u64,
+    /// The hosts callback URLs may point to. Any host resolving to public addresses is allowed if
+    /// empty. Listed hosts are trusted and may resolve to private addresses.
+    pub allowed_hosts: Vec<String>,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            default_url: None,
+            secret: None,
+            max_attempts: 5,
+            timeout_secs: 10,
+            allowed_hosts: vec![],
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImapConfig {
+    /// Flag to poll replies from the mailbox instead of waiting for them on `/api/receiveEmail`.
+    pub enabled: bool,
+    /// The hostname of the IMAP server.
+    pub host: String,
+    /// The port of the IMAP server, which is connected to over TLS.
+    pub port: u16,
+    /// The username to log in with.
+    pub username: String,
+    /// The password to log in with.
+    pub password: String,
+    /// The mailbox replies are read from.
+    pub mailbox: String,
+    /// The mailbox processed replies are moved to. Processed replies are left in place if unset.
+    pub processed_mailbox: Option<String>,
+    /// Flag to wait for new replies with IDLE if the server supports it.
+    pub idle: bool,
+    /// The interval in seconds at which the mailbox is polled without IDLE.
+    pub poll_interval_secs: u64,
+}
+
+impl Default for ImapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 993,
+            username: String::new(),
+            password: String::new(),
+            mailbox: "INBOX".to_string(),
+            processed_mailbox: Some("Processed".to_string()),
+            idle: true,
+            poll_interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LmtpConfig {
+    /// Flag to accept replies delivered to the relayer over SMTP or LMTP.
+    pub enabled: bool,
+    /// The address the listener binds to, e.g. `127.0.0.1:2424`.
+    pub bind_address: String,
+    /// The protocol spoken by the listener.
+    pub protocol: MailProtocol,
+    /// The hostname announced in the greeting.
+    pub hostname: String,
+    /// The addresses mail is accepted for. Defaults to the sender address of the SMTP transport.
+    /// Must be set when speaking SMTP. Over LMTP, mail for any address is accepted if neither is set.
+    pub recipients: Vec<String>,
+    /// The maximum size in bytes of an accepted message.
+    pub max_message_bytes: usize,
+}
+
+impl Default for LmtpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:2424".to_string(),
+            protocol: MailProtocol::Lmtp,
+            hostname: "localhost".to_string(),
+            recipients: Vec::new(),
+            max_message_bytes: 10 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum MailProtocol {
+    /// LMTP, for delivery from a local mail server.
+    Lmtp,
+    /// SMTP, for delivery straight from the sender's mail server.
+    Smtp,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DkimConfig {
+    /// The number of seconds a registered key is trusted without checking the registry again.
+    pub cache_ttl_secs: u64,
+    /// Flag to register the keys of popular domains before replies from them arrive.
+    pub sync_enabled: bool,
+    /// The interval in seconds at which the keys of popular domains are checked.
+    pub sync_interval_secs: u64,
+    /// The number of most used selectors whose keys are checked.
+    pub sync_top_selectors: u32,
+    /// Selectors whose keys are always checked, in addition to the most used ones.
+    pub preregister: Vec<DkimSelectorConfig>,
+    /// The registries the synced keys are registered in. Only these are written to, as the
+    /// registries of past requests were chosen by API clients.
+    pub sync_registries: Vec<DkimRegistryConfig>,
+    /// Flag to check whether the registered keys are still published by their domains.
+    pub revocation_check_enabled: bool,
+    /// The interval in seconds at which the registered keys are checked.
+    pub revocation_check_interval_secs: u64,
+    /// The number of consecutive checks a selector must publish an empty key for before the key
+    /// is considered revoked.
+    pub revocation_confirmations: u32,
+    /// Flag to revoke keys in `ECDSAOwnedDKIMRegistry` registries, signed with the key of the
+    /// chain's `dns` key source.
+    pub submit_revocations: bool,
+}
+
+impl Default for DkimConfig {
+    fn default() -> Self {
+        Self {
+            cache_ttl_secs: 60 * 60,
+            sync_enabled: false,
+            sync_interval_secs: 60 * 60,
+            sync_top_selectors: 50,
+            preregister: Vec::new(),
+            sync_registries: Vec::new(),
+            revocation_check_enabled: true,
+            revocation_check_interval_secs: 6 * 60 * 60,
+            revocation_confirmations: 3,
+            submit_revocations: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DkimSelectorConfig {
+    /// The domain of the key.
+    pub domain: String,
+    /// The DKIM selector of the key.
+    pub selector: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DkimRegistryConfig {
+    /// The chain of the registry, a key of `chains`.
+    pub chain: String,
+    /// The address of the registry.
+    pub registry_address: String,
+}
+
 // Function to load the configuration from a JSON file
 pub fn load_config() -> Result<Config, Error> {
     // Open the configuration file
@@ -76,6 +615,8 @@ pub fn load_config() -> Result<Config, Error> {
     let config: Config = serde_json::from_str(&data)
         .map_err(|e| anyhow::anyhow!("Failed to parse config file: {}", e))?;
 
+    config.validate()?;
+
     // Setting Logger ENV
     if config.json_logger {
         env::set_var("JSON_LOGGER", "true");
@@ -83,3 +624,212 @@ pub fn load_config() -> Result<Config, Error> {
 
     Ok(config)
 }
+
+impl Config {
+    /// Checks settings that parse but cannot work together.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating whether the configuration is usable.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.auth.enabled {
+            if self.auth.admin_token.as_deref() == Some("") {
+                return Err(anyhow::anyhow!(
+                    "auth.adminToken is empty, set it or remove it to disable the admin API"
+                ));
+            }
+            if self
+                .auth
+                .receive_email_secret
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return Err(anyhow::anyhow!(
+                    "auth.receiveEmailSecret must be set when auth is enabled"
+                ));
+            }
+            if self
+                .auth
+                .stream_token_secret
+                .as_deref()
+                .unwrap_or_default()
+                .is_empty()
+            {
+                return Err(anyhow::anyhow!(
+                    "auth.streamTokenSecret must be set when auth is enabled"
+                ));
+            }
+        }
+
+        if self.lmtp.enabled
+            && self.lmtp.protocol == MailProtocol::Smtp
+            && self.lmtp.recipients.is_empty()
+        {
+            return Err(anyhow::anyhow!(
+                "lmtp.recipients must list the relayer's addresses when the protocol is smtp"
+            ));
+        }
+
+        if !self.prover_pool.endpoints.is_empty()
+            && self
+                .prover_pool
+                .endpoints
+                .iter()
+                .all(|endpoint| endpoint.weight == 0)
+        {
+            return Err(anyhow::anyhow!(
+                "proverPool.endpoints are all disabled with a weight of 0"
+            ));
+        }
+
+        if self.dkim.sync_enabled {
+            if self.dkim.sync_registries.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "dkim.syncRegistries must list the registries to sync when the sync is enabled"
+                ));
+            }
+            if let Some(registry) = self
+                .dkim
+                .sync_registries
+                .iter()
+                .find(|registry| !self.chains.contains_key(&registry.chain))
+            {
+                return Err(anyhow::anyhow!(
+                    "dkim.syncRegistries lists unknown chain {}",
+                    registry.chain
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the address the relayer sends emails from, if it sends them over SMTP itself.
+    pub fn sending_address(&self) -> Option<String> {
+        let EmailTransportConfig::Smtp(smtp_config) = &self.email_transport else {
+            return None;
+        };
+        // The sender may be given with a display name, e.g. `Relayer <relayer@example.com>`
+        let from = smtp_config.from.trim();
+        let address = match (from.rfind('<'), from.rfind('>')) {
+            (Some(start), Some(end)) if start < end => &from[start + 1..end],
+            _ => from,
+        };
+        Some(address.trim().to_string()).filter(|address| !address.is_empty())
+    }
+}
+
+/// Stands in for a secret in the `Debug` output of the configuration, so it is not logged.
+const REDACTED: &str = "<redacted>";
+
+/// Redacts an optional secret, keeping whether it is set.
+fn redact(secret: &Option<String>) -> Option<&'static str> {
+    secret.as_ref().map(|_| REDACTED)
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field("port", &self.port)
+            .field("database_url", &REDACTED)
+            .field("smtp_url", &self.smtp_url)
+            .field("email_transport", &self.email_transport)
+            .field("prover_url", &self.prover_url)
+            .field("prover", &self.prover)
+            .field("prover_pool", &self.prover_pool)
+            .field("circuit_profiles", &self.circuit_profiles)
+            .field("path", &self.path)
+            .field("icp", &self.icp)
+            .field("chains", &self.chains)
+            .field("json_logger", &self.json_logger)
+            .field("notify_sender_mismatch", &self.notify_sender_mismatch)
+            .field("queue", &self.queue)
+            .field("auth", &self.auth)
+            .field("rate_limit", &self.rate_limit)
+            .field("expiry", &self.expiry)
+            .field("webhook", &self.webhook)
+            .field("imap", &self.imap)
+            .field("lmtp", &self.lmtp)
+            .field("dkim", &self.dkim)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ChainConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainConfig")
+            .field("private_key", &REDACTED)
+            .field("rpc_url", &self.rpc_url)
+            .field("chain_id", &self.chain_id)
+            .field("dkim_key_source", &self.dkim_key_source)
+            .finish()
+    }
+}
+
+impl fmt::Debug for DkimKeySourceConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DkimKeySourceConfig::Icp => f.write_str("Icp"),
+            DkimKeySourceConfig::Dns { .. } => f
+                .debug_struct("Dns")
+                .field("signer_private_key", &REDACTED)
+                .finish(),
+            DkimKeySourceConfig::File { path } => {
+                f.debug_struct("File").field("path", path).finish()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &redact(&self.password))
+            .field("tls", &self.tls)
+            .field("from", &self.from)
+            .finish()
+    }
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("enabled", &self.enabled)
+            .field("admin_token", &redact(&self.admin_token))
+            .field("receive_email_secret", &redact(&self.receive_email_secret))
+            .field("stream_token_secret", &redact(&self.stream_token_secret))
+            .finish()
+    }
+}
+
+impl fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("default_url", &self.default_url)
+            .field("secret", &redact(&self.secret))
+            .field("max_attempts", &self.max_attempts)
+            .field("timeout_secs", &self.timeout_secs)
+            .field("allowed_hosts", &self.allowed_hosts)
+            .finish()
+    }
+}
+
+impl fmt::Debug for ImapConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImapConfig")
+            .field("enabled", &self.enabled)
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &REDACTED)
+            .field("mailbox", &self.mailbox)
+            .field("processed_mailbox", &self.processed_mailbox)
+            .field("idle", &self.idle)
+            .field("poll_interval_secs", &self.poll_interval_secs)
+            .finish()
+    }
+}
@@ -4,8 +4,11 @@ pub const SHA_PRECOMPUTE_SELECTOR: &str = r#"(<(=\r\n)?d(=\r\n)?i(=\r\n)?v(=\r\n
 /// Regex for capturing the request ID from an email body.
 pub const REQUEST_ID_REGEX: &str = r"(Your request ID is )([0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12})";
 
-/// The number of fields expected in a domain configuration.
-pub const DOMAIN_FIELDS: usize = 9;
+/// The name of the circuit profile used by requests that do not select one.
+pub const DEFAULT_CIRCUIT_PROFILE: &str = "default";
 
-/// The number of fields expected in a command configuration.
-pub const COMMAND_FIELDS: usize = 20;
+/// The maximum number of requests in a batch submission.
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// The prefixes mail clients add to the subject of replies and forwards, in lowercase.
+pub const REPLY_SUBJECT_PREFIXES: [&str; 3] = ["re:", "fwd:", "fw:"];
@@ -1,13 +1,26 @@
-use abis::UserOverridableDKIMRegistry;
-use anyhow::anyhow;
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use base64::Engine;
 use candid::Encode;
 use chain::ChainClient;
+use config::{ChainConfig, DkimKeySourceConfig};
+use ethers::signers::{LocalWallet, Signer};
 use ethers::types::Address;
 use ethers::utils::hex;
+use model::{touch_cached_dkim_key, upsert_dkim_key, DkimKeyModel};
 use relayer_utils::fr_to_bytes32;
 use relayer_utils::public_key_hash;
 use relayer_utils::ParsedEmail;
 use relayer_utils::LOG;
+use rsa::pkcs1::DecodeRsaPublicKey;
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use schema::DkimRegistryType;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::TokioAsyncResolver;
 
 use crate::*;
 use candid::CandidType;
@@ -23,6 +36,12 @@ use serde::Deserialize;
 ///  Amount of cycles charged by the ICP canister
 pub const SIGN_CHARGED_CYCLE: u128 = 39_246_898_590;
 
+/// The prefix of messages authorizing the registration of a public key hash.
+pub const SET_PREFIX: &str = "SET:";
+
+/// The prefix of messages authorizing the revocation of a public key hash.
+pub const REVOKE_PREFIX: &str = "REVOKE:";
+
 /// Represents a client for interacting with the DKIM Oracle.
 #[derive(Debug, Clone)]
 pub struct DkimOracleClient<'a> {
@@ -140,15 +159,354 @@ impl<'a> DkimOracleClient<'a> {
     }
 }
 
+/// A source of DKIM public keys signed for registration in a DKIM registry.
+#[async_trait]
+pub trait DkimKeySource: Send + Sync {
+    /// Gets the signed public key of a DKIM selector.
+    ///
+    /// # Arguments
+    ///
+    /// * `selector` - The selector for the DKIM key.
+    /// * `domain` - The domain for the DKIM key.
+    ///
+    /// # Returns
+    ///
+    /// An `anyhow::Result<SignedDkimPublicKey>`.
+    async fn get_signed_public_key(
+        &self,
+        selector: &str,
+        domain: &str,
+    ) -> anyhow::Result<SignedDkimPublicKey>;
+}
+
+/// Creates the DKIM key source configured for a chain.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the relayer.
+/// * `chain_config` - The configuration of the chain.
+///
+/// # Returns
+///
+/// An `anyhow::Result` containing the key source.
+pub fn create_dkim_key_source(
+    config: &Config,
+    chain_config: &ChainConfig,
+) -> anyhow::Result<Box<dyn DkimKeySource>> {
+    let key_source: Box<dyn DkimKeySource> = match &chain_config.dkim_key_source {
+        DkimKeySourceConfig::Icp => Box::new(IcpKeySource {
+            pem_path: config.path.pem.clone(),
+            ic_replica_url: config.icp.ic_replica_url.clone(),
+            dkim_canister_id: config.icp.dkim_canister_id.clone(),
+            wallet_canister_id: config.icp.wallet_canister_id.clone(),
+        }),
+        DkimKeySourceConfig::Dns { signer_private_key } => Box::new(DnsKeySource {
+            resolver: TokioAsyncResolver::tokio_from_system_conf()?,
+            signer: signer_private_key.parse()?,
+        }),
+        DkimKeySourceConfig::File { path } => Box::new(FileKeySource { path: path.clone() }),
+    };
+    Ok(key_source)
+}
+
+/// Computes the message signed to set or revoke a public key hash in a DKIM registry.
+///
+/// This matches `computeSignedMsg` of `ECDSAOwnedDKIMRegistry`, which formats the hash with
+/// `Strings.toHexString`, i.e. without leading zero bytes.
+///
+/// # Arguments
+///
+/// * `prefix` - The operation prefix, `SET_PREFIX` or `REVOKE_PREFIX`.
+/// * `domain` - The domain of the public key.
+/// * `public_key_hash` - The hash of the public key.
+///
+/// # Returns
+///
+/// The message to sign.
+pub fn compute_signed_msg(prefix: &str, domain: &str, public_key_hash: &[u8; 32]) -> String {
+    let start = public_key_hash
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(public_key_hash.len() - 1);
+    format!(
+        "{}domain={};public_key_hash=0x{};",
+        prefix,
+        domain,
+        hex::encode(&public_key_hash[start..])
+    )
+}
+
+/// Gets signed public keys from the DKIM oracle canister on ICP.
+pub struct IcpKeySource {
+    pem_path: String,
+    ic_replica_url: String,
+    dkim_canister_id: String,
+    wallet_canister_id: String,
+}
+
+#[async_trait]
+impl DkimKeySource for IcpKeySource {
+    async fn get_signed_public_key(
+        &self,
+        selector: &str,
+        domain: &str,
+    ) -> anyhow::Result<SignedDkimPublicKey> {
+        // Generate IC agent and create oracle client
+        let ic_agent = DkimOracleClient::gen_agent(&self.pem_path, &self.ic_replica_url)?;
+        info!(LOG, "ic_agent {:?}", ic_agent);
+
+        info!(LOG, "icp canister id {:?}", &self.dkim_canister_id);
+        info!(LOG, "icp replica url {:?}", &self.ic_replica_url);
+
+        let oracle_client =
+            DkimOracleClient::new(&self.dkim_canister_id, &self.wallet_canister_id, &ic_agent)
+                .await?;
+        info!(LOG, "oracle_client {:?}", oracle_client);
+
+        // Request signature from oracle
+        oracle_client.request_signature(selector, domain).await
+    }
+}
+
+/// Looks up public keys in DNS and signs them with a local key.
+pub struct DnsKeySource {
+    resolver: TokioAsyncResolver,
+    signer: LocalWallet,
+}
+
+/// The state of a DKIM selector in DNS.
+#[derive(Debug)]
+pub enum PublishedKey {
+    /// The selector publishes a key.
+    Key(RsaPublicKey),
+    /// The selector publishes an empty key, which revokes it (RFC 6376 section 3.6.1).
+    Empty,
+    /// The selector has no key record.
+    Missing,
+}
+
+/// Looks up the RSA public key published in DNS for a DKIM selector.
+///
+/// # Arguments
+///
+/// * `resolver` - The DNS resolver.
+/// * `selector` - The selector for the DKIM key.
+/// * `domain` - The domain for the DKIM key.
+///
+/// # Returns
+///
+/// An `anyhow::Result` containing the published key, or whether it is empty or missing.
+pub async fn lookup_published_key(
+    resolver: &TokioAsyncResolver,
+    selector: &str,
+    domain: &str,
+) -> anyhow::Result<PublishedKey> {
+    let name = format!("{}._domainkey.{}.", selector, domain);
+    let lookup = match resolver.txt_lookup(name.clone()).await {
+        Ok(lookup) => lookup,
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            return Ok(PublishedKey::Missing)
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // A TXT record may be split into several strings, which are concatenated
+    let Some(record) = lookup
+        .iter()
+        .map(|txt| {
+            let data: Vec<u8> = txt.txt_data().iter().flat_map(|d| d.to_vec()).collect();
+            String::from_utf8_lossy(&data).into_owned()
+        })
+        .find(|record| record.contains("p="))
+    else {
+        return Ok(PublishedKey::Missing);
+    };
+
+    let Some(public_key) = record
+        .split(';')
+        .find_map(|tag| tag.trim().strip_prefix("p="))
+        .map(|value| value.split_whitespace().collect::<String>())
+    else {
+        return Ok(PublishedKey::Missing);
+    };
+    if public_key.is_empty() {
+        return Ok(PublishedKey::Empty);
+    }
+    let der = base64::engine::general_purpose::STANDARD.decode(public_key)?;
+
+    RsaPublicKey::from_public_key_der(&der)
+        .or_else(|_| RsaPublicKey::from_pkcs1_der(&der))
+        .map(PublishedKey::Key)
+        .map_err(|e| anyhow!("Invalid DKIM public key at {}: {}", name, e))
+}
+
+/// Looks up the RSA public key published in DNS for a DKIM selector, failing if there is none.
+///
+/// # Arguments
+///
+/// * `resolver` - The DNS resolver.
+/// * `selector` - The selector for the DKIM key.
+/// * `domain` - The domain for the DKIM key.
+///
+/// # Returns
+///
+/// An `anyhow::Result` containing the public key.
+pub async fn lookup_public_key(
+    resolver: &TokioAsyncResolver,
+    selector: &str,
+    domain: &str,
+) -> anyhow::Result<RsaPublicKey> {
+    match lookup_published_key(resolver, selector, domain).await? {
+        PublishedKey::Key(public_key) => Ok(public_key),
+        PublishedKey::Empty | PublishedKey::Missing => Err(anyhow!(
+            "No DKIM key published at {}._domainkey.{}",
+            selector,
+            domain
+        )),
+    }
+}
+
+/// Computes the hash of a public key, as registered in DKIM registries.
+///
+/// # Arguments
+///
+/// * `public_key` - The RSA public key.
+///
+/// # Returns
+///
+/// An `anyhow::Result` containing the hash.
+pub fn rsa_public_key_hash(public_key: &RsaPublicKey) -> anyhow::Result<[u8; 32]> {
+    let public_key_n = public_key.n().to_bytes_le();
+    fr_to_bytes32(&public_key_hash(&public_key_n)?)
+}
+
+#[async_trait]
+impl DkimKeySource for DnsKeySource {
+    async fn get_signed_public_key(
+        &self,
+        selector: &str,
+        domain: &str,
+    ) -> anyhow::Result<SignedDkimPublicKey> {
+        let public_key = lookup_public_key(&self.resolver, selector, domain).await?;
+        let public_key_hash = rsa_public_key_hash(&public_key)?;
+
+        let signed_msg = compute_signed_msg(SET_PREFIX, domain, &public_key_hash);
+        let signature = self.signer.sign_message(signed_msg).await?;
+
+        Ok(SignedDkimPublicKey {
+            selector: selector.to_string(),
+            domain: domain.to_string(),
+            signature: format!("0x{}", hex::encode(signature.to_vec())),
+            public_key: format!("0x{}", hex::encode(public_key.n().to_bytes_be())),
+            public_key_hash: format!("0x{}", hex::encode(public_key_hash)),
+        })
+    }
+}
+
+/// Reads signed public keys from a JSON file holding a list of `SignedDkimPublicKey`.
+pub struct FileKeySource {
+    path: String,
+}
+
+#[async_trait]
+impl DkimKeySource for FileKeySource {
+    async fn get_signed_public_key(
+        &self,
+        selector: &str,
+        domain: &str,
+    ) -> anyhow::Result<SignedDkimPublicKey> {
+        let data = tokio::fs::read_to_string(&self.path)
+            .await
+            .with_context(|| format!("Failed to read DKIM keys from {}", self.path))?;
+        let keys: Vec<SignedDkimPublicKey> = serde_json::from_str(&data)?;
+
+        keys.into_iter()
+            .find(|key| key.selector == selector && key.domain.eq_ignore_ascii_case(domain))
+            .ok_or_else(|| anyhow!("No DKIM key for {} in {}", domain, self.path))
+    }
+}
+
+/// Verifies the DKIM signature of an email against the public key it is proven with.
+///
+/// This checks the body hash in the `DKIM-Signature` header and the RSA signature over the
+/// canonicalized header, so tampered emails are rejected before any proving work is done.
+///
+/// # Arguments
+///
+/// * `parsed_email` - The parsed email data.
+///
+/// # Returns
+///
+/// An `anyhow::Result<()>` describing the failed check, if any.
+pub fn verify_dkim_signature(parsed_email: &ParsedEmail) -> anyhow::Result<()> {
+    // The signature header is part of the signed header, with an empty `b=` tag
+    let header = &parsed_email.canonicalized_header;
+    let signature_header = header
+        .split("\r\n")
+        .find_map(|line| {
+            line.split_once(':')
+                .filter(|(name, _)| name.trim().eq_ignore_ascii_case("dkim-signature"))
+                .map(|(_, value)| value)
+        })
+        .ok_or_else(|| anyhow!("Email has no DKIM-Signature header"))?;
+    let tags: HashMap<String, String> = signature_header
+        .split(';')
+        .filter_map(|tag| tag.split_once('='))
+        .map(|(name, value)| {
+            (
+                name.trim().to_ascii_lowercase(),
+                value.split_whitespace().collect(),
+            )
+        })
+        .collect();
+
+    let algorithm = tags.get("a").map(String::as_str).unwrap_or_default();
+    if algorithm != "rsa-sha256" {
+        return Err(anyhow!("Unsupported DKIM algorithm: {:?}", algorithm));
+    }
+
+    // Check the body hash, over the first `l=` bytes of the body if set
+    let body = parsed_email.canonicalized_body.as_bytes();
+    let body = match tags.get("l") {
+        Some(length) => {
+            let length: usize = length.parse()?;
+            body.get(..length)
+                .ok_or_else(|| anyhow!("DKIM body length exceeds the body"))?
+        }
+        None => body,
+    };
+    let body_hash = base64::engine::general_purpose::STANDARD.decode(
+        tags.get("bh")
+            .ok_or_else(|| anyhow!("DKIM-Signature has no body hash"))?,
+    )?;
+    if Sha256::digest(body).as_slice() != body_hash.as_slice() {
+        return Err(anyhow!("Body hash does not match the DKIM-Signature"));
+    }
+
+    // Check the signature over the header with the key that will be registered
+    let public_key = RsaPublicKey::new(
+        BigUint::from_bytes_be(&parsed_email.public_key),
+        BigUint::from(65537u32),
+    )?;
+    public_key
+        .verify(
+            Pkcs1v15Sign::new::<Sha256>(),
+            &Sha256::digest(header.as_bytes()),
+            &parsed_email.signature,
+        )
+        .map_err(|_| anyhow!("DKIM signature does not match the header"))
+}
+
 /// Checks and updates the DKIM for a given email.
 ///
 /// # Arguments
 ///
-/// * `email` - The email address.
 /// * `parsed_email` - The parsed email data.
-/// * `controller_eth_addr` - The Ethereum address of the controller.
-/// * `wallet_addr` - The address of the wallet.
-/// * `account_salt` - The salt for the account.
+/// * `dkim` - The address of the DKIM registry.
+/// * `dkim_registry_type` - The type of the DKIM registry, detected on-chain if `None`.
+/// * `chain` - The name of the chain the registry is on.
+/// * `chain_client` - The client of the chain.
+/// * `relayer_state` - The current state of the relayer.
 ///
 /// # Returns
 ///
@@ -156,6 +514,8 @@ impl<'a> DkimOracleClient<'a> {
 pub async fn check_and_update_dkim(
     parsed_email: &ParsedEmail,
     dkim: Address,
+    dkim_registry_type: Option<DkimRegistryType>,
+    chain: &str,
     chain_client: ChainClient,
     relayer_state: RelayerState,
 ) -> Result<()> {
@@ -169,64 +529,91 @@ pub async fn check_and_update_dkim(
     let domain = parsed_email.get_email_domain()?;
     info!(LOG, "domain {:?}", domain);
 
+    // Get selector using regex
+    let regex_pattern = r"((\r\n)|^)dkim-signature:([a-z]+=[^;]+; )+s=([0-9a-z_-]+);";
+    let re = regex::Regex::new(regex_pattern).map_err(|e| anyhow!("Invalid regex: {}", e))?;
+
+    let selector = re
+        .captures(&parsed_email.canonicalized_header)
+        .and_then(|caps| caps.get(4))
+        .map(|m| m.as_str().to_string())
+        .ok_or_else(|| anyhow!("Failed to extract selector using regex"))?;
+
+    info!(LOG, "selector {}", selector);
+
+    let key = DkimKeyModel {
+        chain: chain.to_string(),
+        registry_address: format!("{:?}", dkim),
+        domain,
+        selector,
+        public_key_hash: format!("0x{}", hex::encode(fr_to_bytes32(&public_key_hash)?)),
+    };
+    ensure_dkim_key_registered(&relayer_state, &chain_client, &key, dkim_registry_type, 1).await
+}
+
+/// Makes sure a DKIM key is registered, registering it through the key source of the chain if not.
+///
+/// Keys found registered are cached, so the registry is only checked again once the cache entry
+/// is older than `dkim.cacheTtlSecs`.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `chain_client` - The client of the chain the registry is on.
+/// * `key` - The key in the registry.
+/// * `dkim_registry_type` - The type of the DKIM registry, detected on-chain if `None`.
+/// * `hits` - The number of uses of the key to record.
+///
+/// # Returns
+///
+/// A `Result<()>`.
+pub async fn ensure_dkim_key_registered(
+    relayer_state: &RelayerState,
+    chain_client: &ChainClient,
+    key: &DkimKeyModel,
+    dkim_registry_type: Option<DkimRegistryType>,
+    hits: i32,
+) -> Result<()> {
+    let db = &relayer_state.db;
+    if touch_cached_dkim_key(db, key, relayer_state.config.dkim.cache_ttl_secs, hits).await? {
+        info!(LOG, "public key registered (cached)");
+        return Ok(());
+    }
+
     // Get DKIM
-    let dkim = UserOverridableDKIMRegistry::new(dkim, chain_client.client.clone());
+    let dkim = chain_client
+        .dkim_registry(key.registry_address.parse()?, dkim_registry_type)
+        .await?;
 
     info!(LOG, "dkim {:?}", dkim);
 
     // Check if DKIM public key hash is valid
+    let public_key_hash = hex::decode(&key.public_key_hash[2..])?;
     if chain_client
         .check_if_dkim_public_key_hash_valid(
-            domain.clone(),
-            fr_to_bytes32(&public_key_hash)?,
-            dkim.clone(),
+            key.domain.clone(),
+            TryInto::<[u8; 32]>::try_into(public_key_hash)
+                .map_err(|_| anyhow!("Invalid public key hash"))?,
+            &dkim,
         )
         .await?
     {
         info!(LOG, "public key registered");
+        upsert_dkim_key(db, key, true, hits).await?;
         return Ok(());
     }
 
-    // Get selector using regex
-    let regex_pattern = r"((\r\n)|^)dkim-signature:([a-z]+=[^;]+; )+s=([0-9a-z_-]+);";
-    let re = regex::Regex::new(regex_pattern).map_err(|e| anyhow!("Invalid regex: {}", e))?;
-
-    let selector = re
-        .captures(&parsed_email.canonicalized_header)
-        .and_then(|caps| caps.get(4))
-        .map(|m| m.as_str().to_string())
-        .ok_or_else(|| anyhow!("Failed to extract selector using regex"))?;
-
-    info!(LOG, "selector {}", selector);
-
-    // Generate IC agent and create oracle client
-    let ic_agent = DkimOracleClient::gen_agent(
-        &relayer_state.config.path.pem,
-        &relayer_state.config.icp.ic_replica_url,
-    )?;
-    info!(LOG, "ic_agent {:?}", ic_agent);
-
-    info!(
-        LOG,
-        "icp canister id {:?}", &relayer_state.config.icp.dkim_canister_id
-    );
-    info!(
-        LOG,
-        "icp replica url {:?}", &relayer_state.config.icp.ic_replica_url
-    );
-
-    let oracle_client = DkimOracleClient::new(
-        &relayer_state.config.icp.dkim_canister_id,
-        &relayer_state.config.icp.wallet_canister_id,
-        &ic_agent,
-    )
-    .await?;
-    info!(LOG, "oracle_client {:?}", oracle_client);
-
-    // Request signature from oracle
-    let oracle_result = oracle_client.request_signature(&selector, &domain).await?;
+    // Get the signed public key from the key source of the chain
+    let chain_config = relayer_state
+        .config
+        .chains
+        .get(&key.chain)
+        .ok_or_else(|| anyhow!("Chain configuration not found"))?;
+    let key_source = create_dkim_key_source(&relayer_state.config, chain_config)?;
+    let oracle_result = key_source
+        .get_signed_public_key(&key.selector, &key.domain)
+        .await?;
     info!(LOG, "DKIM oracle result {:?}", oracle_result);
-
     // Process oracle response
     let public_key_hash = hex::decode(&oracle_result.public_key_hash[2..])?;
     info!(LOG, "public_key_hash from oracle {:?}", public_key_hash);
@@ -236,12 +623,127 @@ pub async fn check_and_update_dkim(
     // Set DKIM public key hash
     let tx_hash = chain_client
         .set_dkim_public_key_hash(
-            domain,
-            TryInto::<[u8; 32]>::try_into(public_key_hash).unwrap(),
+            key.selector.clone(),
+            key.domain.clone(),
+            TryInto::<[u8; 32]>::try_into(public_key_hash.clone())
+                .map_err(|_| anyhow!("Invalid public key hash from oracle"))?,
             signature,
-            dkim,
+            &dkim,
         )
         .await?;
     info!(LOG, "DKIM registry updated {:?}", tx_hash);
+
+    // The oracle signs the key currently published, which differs from the key if it rotated
+    let registered_key = DkimKeyModel {
+        public_key_hash: format!("0x{}", hex::encode(public_key_hash)),
+        ..key.clone()
+    };
+    upsert_dkim_key(db, &registered_key, true, hits).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds the message `ECDSAOwnedDKIMRegistry.computeSignedMsg` returns, whose hash is
+    /// formatted by `Strings.toHexString(uint256)` with the minimal number of bytes.
+    fn expected_msg(prefix: &str, domain: &str, hex_hash: &str) -> String {
+        format!(
+            "{}domain={};public_key_hash=0x{};",
+            prefix, domain, hex_hash
+        )
+    }
+
+    #[test]
+    fn compute_signed_msg_keeps_full_hash() {
+        let public_key_hash: [u8; 32] =
+            hex::decode("1f2e3d4c5b6a798897a6b5c4d3e2f1001f2e3d4c5b6a798897a6b5c4d3e2f100")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            compute_signed_msg(SET_PREFIX, "example.com", &public_key_hash),
+            expected_msg(
+                SET_PREFIX,
+                "example.com",
+                "1f2e3d4c5b6a798897a6b5c4d3e2f1001f2e3d4c5b6a798897a6b5c4d3e2f100"
+            )
+        );
+    }
+
+    #[test]
+    fn compute_signed_msg_drops_leading_zero_bytes() {
+        let mut public_key_hash = [0u8; 32];
+        public_key_hash[1..].copy_from_slice(
+            &hex::decode("0a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8").unwrap(),
+        );
+        // Only whole zero bytes are dropped, the leading zero nibble of 0x0a is kept
+        assert_eq!(
+            compute_signed_msg(REVOKE_PREFIX, "example.com", &public_key_hash),
+            expected_msg(
+                REVOKE_PREFIX,
+                "example.com",
+                "0a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f60718293a4b5c6d7e8"
+            )
+        );
+    }
+
+    #[test]
+    fn compute_signed_msg_formats_small_hashes_like_to_hex_string() {
+        // `computeSignedMsg.t.sol` signs bytes32(uint256(1)), which `toHexString` formats as 0x01
+        let mut public_key_hash = [0u8; 32];
+        public_key_hash[31] = 1;
+        assert_eq!(
+            compute_signed_msg(SET_PREFIX, "example.com", &public_key_hash),
+            expected_msg(SET_PREFIX, "example.com", "01")
+        );
+
+        assert_eq!(
+            compute_signed_msg(SET_PREFIX, "example.com", &[0u8; 32]),
+            expected_msg(SET_PREFIX, "example.com", "00")
+        );
+    }
+
+    /// A reply signed by gmail.com, shared with the circuit tests.
+    const SIGNED_EMAIL: &str = include_str!("../../circuits/tests/emails/email_auth_test1.eml");
+
+    /// Parses an email, which looks up the signing key of gmail.com in DNS.
+    async fn parse(raw_email: &str) -> ParsedEmail {
+        ParsedEmail::new_from_raw_email(raw_email).await.unwrap()
+    }
+
+    /// Replaces a part of the signed email, checking that it is there.
+    fn tamper(from: &str, to: &str) -> String {
+        assert!(SIGNED_EMAIL.contains(from));
+        SIGNED_EMAIL.replacen(from, to, 1)
+    }
+
+    #[tokio::test]
+    #[ignore = "parsing the email looks up the DKIM key of gmail.com in DNS"]
+    async fn verify_dkim_signature_accepts_signed_email() {
+        verify_dkim_signature(&parse(SIGNED_EMAIL).await).unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "parsing the email looks up the DKIM key of gmail.com in DNS"]
+    async fn verify_dkim_signature_rejects_tampered_body() {
+        let raw_email = tamper("Send 0.1 ETH", "Send 9.1 ETH");
+        let err = verify_dkim_signature(&parse(&raw_email).await).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Body hash does not match the DKIM-Signature"
+        );
+    }
+
+    #[tokio::test]
+    #[ignore = "parsing the email looks up the DKIM key of gmail.com in DNS"]
+    async fn verify_dkim_signature_rejects_tampered_header() {
+        let raw_email = tamper(
+            "Subject: [Reply Needed] Test Email 1",
+            "Subject: [Reply Needed] Test Email 2",
+        );
+        let err = verify_dkim_signature(&parse(&raw_email).await).unwrap_err();
+        assert_eq!(err.to_string(), "DKIM signature does not match the header");
+    }
+}
@@ -0,0 +1,183 @@
+//! Detection of DKIM keys revoked by their domains.
+//!
+//! The monitor compares the keys registered for the domains requests were sent to with the ones
+//! published in DNS. A selector that publishes a different key or no record at all is only
+//! flagged, as domains rotate keys and DNS lookups fail transiently. A key is revoked only by an
+//! explicitly empty `p=` tag (RFC 6376 section 3.6.1), seen in `revocationConfirmations`
+//! consecutive checks. Flagged keys are listed on the admin API and, if enabled, revoked keys are
+//! revoked in `ECDSAOwnedDKIMRegistry` registries with the signer of the chain's `dns` key source.
+//! Revocations that fail to submit are retried on the next check.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    signers::{LocalWallet, Signer},
+    types::Bytes,
+    utils::hex,
+};
+use relayer_utils::LOG;
+use slog::{error, info, warn};
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::{
+    chain::ChainClient,
+    config::DkimKeySourceConfig,
+    dkim::{
+        compute_signed_msg, lookup_published_key, rsa_public_key_hash, PublishedKey, REVOKE_PREFIX,
+    },
+    model::{
+        clear_dkim_key_flag, flag_dkim_key, get_dkim_keys_to_monitor, get_dkim_keys_to_revoke,
+        record_empty_dkim_key, set_dkim_key_revocation_tx, DkimKeyModel,
+    },
+    RelayerState,
+};
+
+/// Runs the revocation monitor until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_revocation_monitor(relayer_state: RelayerState) {
+    let check_interval = Duration::from_secs(
+        relayer_state
+            .config
+            .dkim
+            .revocation_check_interval_secs
+            .max(1),
+    );
+
+    info!(LOG, "DKIM revocation monitor started");
+    loop {
+        if let Err(e) = check_revocations(&relayer_state).await {
+            error!(LOG, "Failed to check DKIM key revocations: {:?}", e);
+        }
+        tokio::time::sleep(check_interval).await;
+    }
+}
+
+/// Flags the registered keys that are no longer published and revokes the ones confirmed revoked
+/// if enabled.
+async fn check_revocations(relayer_state: &RelayerState) -> Result<()> {
+    let dkim_config = &relayer_state.config.dkim;
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+
+    for key in get_dkim_keys_to_monitor(&relayer_state.db).await? {
+        let reason = match lookup_published_key(&resolver, &key.selector, &key.domain).await {
+            Ok(PublishedKey::Key(public_key)) => {
+                let public_key_hash = rsa_public_key_hash(&public_key)?;
+                if format!("0x{}", hex::encode(public_key_hash)) == key.public_key_hash {
+                    clear_dkim_key_flag(&relayer_state.db, &key).await?;
+                    continue;
+                }
+                "The selector publishes a different key"
+            }
+            Ok(PublishedKey::Missing) => "The selector no longer publishes a key",
+            Ok(PublishedKey::Empty) => {
+                let reason = "The selector publishes an empty key";
+                if record_empty_dkim_key(
+                    &relayer_state.db,
+                    &key,
+                    reason,
+                    dkim_config.revocation_confirmations as i32,
+                )
+                .await?
+                {
+                    warn!(
+                        LOG,
+                        "DKIM key {} of {} ({}) on {} was revoked: {}",
+                        key.selector,
+                        key.domain,
+                        key.public_key_hash,
+                        key.chain,
+                        reason
+                    );
+                }
+                continue;
+            }
+            // A failed lookup says nothing about the key, so it is checked again next time
+            Err(e) => {
+                error!(
+                    LOG,
+                    "Failed to look up DKIM key {} of {}: {:?}", key.selector, key.domain, e
+                );
+                continue;
+            }
+        };
+
+        warn!(
+            LOG,
+            "DKIM key {} of {} ({}) on {} was flagged: {}",
+            key.selector,
+            key.domain,
+            key.public_key_hash,
+            key.chain,
+            reason
+        );
+        flag_dkim_key(&relayer_state.db, &key, reason).await?;
+    }
+
+    if !dkim_config.submit_revocations {
+        return Ok(());
+    }
+    // Revoked keys stay listed until their revocation is submitted, so failures are retried
+    for key in get_dkim_keys_to_revoke(&relayer_state.db).await? {
+        if let Err(e) = submit_revocation(relayer_state, &key).await {
+            error!(
+                LOG,
+                "Failed to revoke DKIM key {} of {} on {}: {:?}",
+                key.selector,
+                key.domain,
+                key.chain,
+                e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Revokes a key in its registry, signed with the signer of the chain's `dns` key source.
+async fn submit_revocation(relayer_state: &RelayerState, key: &DkimKeyModel) -> Result<()> {
+    let chain_config = relayer_state
+        .config
+        .chains
+        .get(&key.chain)
+        .ok_or_else(|| anyhow!("Chain configuration not found"))?;
+    let DkimKeySourceConfig::Dns { signer_private_key } = &chain_config.dkim_key_source else {
+        info!(
+            LOG,
+            "Not revoking DKIM key of {} on {}, the relayer is not its signer",
+            key.domain,
+            key.chain
+        );
+        return Ok(());
+    };
+    let signer: LocalWallet = signer_private_key.parse()?;
+
+    let public_key_hash: [u8; 32] = hex::decode(&key.public_key_hash[2..])?
+        .try_into()
+        .map_err(|_| anyhow!("Invalid public key hash"))?;
+    let signed_msg = compute_signed_msg(REVOKE_PREFIX, &key.domain, &public_key_hash);
+    let signature = signer.sign_message(signed_msg).await?;
+
+    let chain_client =
+        ChainClient::setup(key.chain.clone(), relayer_state.config.chains.clone()).await?;
+    let dkim = chain_client
+        .dkim_registry(key.registry_address.parse()?, None)
+        .await?;
+    let tx_hash = chain_client
+        .revoke_dkim_public_key_hash(
+            key.selector.clone(),
+            key.domain.clone(),
+            public_key_hash,
+            Bytes::from(signature.to_vec()),
+            &dkim,
+        )
+        .await?;
+    info!(
+        LOG,
+        "DKIM key {} of {} revoked on {}: {}", key.selector, key.domain, key.chain, tx_hash
+    );
+
+    set_dkim_key_revocation_tx(&relayer_state.db, key, &tx_hash).await
+}
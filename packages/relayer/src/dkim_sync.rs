@@ -0,0 +1,108 @@
+//! Registration of the DKIM keys of popular domains ahead of replies.
+//!
+//! The sync looks up the current keys of the most used selectors, and of the configured ones, in
+//! DNS and makes sure they are registered in every configured registry. A domain that
+//! rotates its key thus gets the new key registered before the first reply signed with it
+//! arrives, and replies are served from the key cache without calling the oracle.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use ethers::utils::hex;
+use relayer_utils::LOG;
+use slog::{error, info};
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::{
+    chain::ChainClient,
+    config::DkimSelectorConfig,
+    dkim::{ensure_dkim_key_registered, lookup_public_key, rsa_public_key_hash},
+    model::{get_popular_dkim_selectors, DkimKeyModel},
+    RelayerState,
+};
+
+/// Runs the DKIM key sync until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_dkim_sync(relayer_state: RelayerState) {
+    let sync_interval = Duration::from_secs(relayer_state.config.dkim.sync_interval_secs.max(1));
+
+    info!(LOG, "DKIM key sync started");
+    loop {
+        if let Err(e) = sync(&relayer_state).await {
+            error!(LOG, "Failed to sync DKIM keys: {:?}", e);
+        }
+        tokio::time::sleep(sync_interval).await;
+    }
+}
+
+/// Registers the current keys of the popular and configured selectors in every configured registry.
+async fn sync(relayer_state: &RelayerState) -> Result<()> {
+    let dkim_config = &relayer_state.config.dkim;
+    let resolver = TokioAsyncResolver::tokio_from_system_conf()?;
+
+    let mut selectors: Vec<DkimSelectorConfig> =
+        get_popular_dkim_selectors(&relayer_state.db, dkim_config.sync_top_selectors as i64)
+            .await?
+            .into_iter()
+            .map(|(domain, selector)| DkimSelectorConfig { domain, selector })
+            .collect();
+    for selector in &dkim_config.preregister {
+        if !selectors.contains(selector) {
+            selectors.push(selector.clone());
+        }
+    }
+
+    // Look up every selector once, its key is the same for all registries
+    let mut current_keys = Vec::new();
+    for selector in selectors {
+        match lookup_public_key(&resolver, &selector.selector, &selector.domain)
+            .await
+            .and_then(|public_key| rsa_public_key_hash(&public_key))
+        {
+            Ok(public_key_hash) => current_keys.push((selector, public_key_hash)),
+            Err(e) => error!(
+                LOG,
+                "Failed to look up DKIM key {} of {}: {:?}", selector.selector, selector.domain, e
+            ),
+        }
+    }
+
+    for registry in &dkim_config.sync_registries {
+        let chain = &registry.chain;
+        let registry_address = &registry.registry_address;
+        let chain_client =
+            match ChainClient::setup(chain.clone(), relayer_state.config.chains.clone()).await {
+                Ok(chain_client) => chain_client,
+                Err(e) => {
+                    error!(LOG, "Failed to set up chain {}: {:?}", chain, e);
+                    continue;
+                }
+            };
+
+        for (selector, public_key_hash) in &current_keys {
+            let key = DkimKeyModel {
+                chain: chain.clone(),
+                registry_address: registry_address.clone(),
+                domain: selector.domain.clone(),
+                selector: selector.selector.clone(),
+                public_key_hash: format!("0x{}", hex::encode(public_key_hash)),
+            };
+            if let Err(e) =
+                ensure_dkim_key_registered(relayer_state, &chain_client, &key, None, 0).await
+            {
+                error!(
+                    LOG,
+                    "Failed to register DKIM key {} of {} on {}: {:?}",
+                    key.selector,
+                    key.domain,
+                    chain,
+                    e
+                );
+            }
+        }
+    }
+    Ok(())
+}
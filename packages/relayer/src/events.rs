@@ -0,0 +1,174 @@
+//! Live request status events.
+//!
+//! A trigger on the `requests` table publishes every status change on the `request_status`
+//! Postgres channel. Each relayer replica listens on the channel and rebroadcasts the changes
+//! in-process, so a stream opened on any replica sees transitions made by all of them.
+
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use axum::response::sse::Event;
+use relayer_utils::LOG;
+use serde::{Deserialize, Serialize};
+use slog::{error, info};
+use sqlx::postgres::PgListener;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc,
+};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+
+use crate::{
+    model::{get_request, RequestModel, RequestStatus},
+    RelayerState,
+};
+
+/// The Postgres channel status changes are published on.
+pub const STATUS_CHANNEL: &str = "request_status";
+
+/// The number of events buffered for slow subscribers before they start lagging.
+pub const STATUS_EVENTS_CAPACITY: usize = 1024;
+
+/// Represents a status change of a request.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestStatusEvent {
+    /// The unique identifier of the request.
+    pub id: Uuid,
+    /// The new status of the request.
+    pub status: RequestStatus,
+    /// The machine-readable code of the error that ended the request, if any.
+    pub error_code: Option<String>,
+}
+
+impl RequestStatusEvent {
+    /// Builds the event describing the current status of a request.
+    pub fn from_request(request: &RequestModel) -> Self {
+        Self {
+            id: request.id,
+            status: request.status,
+            error_code: request.error_code.clone(),
+        }
+    }
+}
+
+/// The payload of a notification on the status channel, as built by the trigger.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusNotification {
+    id: Uuid,
+    status: String,
+    error_code: Option<String>,
+}
+
+/// Listens for status changes in Postgres and broadcasts them to the open streams.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_status_listener(relayer_state: RelayerState) {
+    loop {
+        if let Err(e) = listen(&relayer_state).await {
+            error!(LOG, "Status listener failed: {:?}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Forwards notifications from the status channel until the connection fails.
+async fn listen(relayer_state: &RelayerState) -> Result<()> {
+    let mut listener = PgListener::connect_with(&relayer_state.db).await?;
+    listener.listen(STATUS_CHANNEL).await?;
+    info!(LOG, "Listening for status changes on {}", STATUS_CHANNEL);
+
+    loop {
+        let notification = listener.recv().await?;
+        let event = match parse_notification(notification.payload()) {
+            Ok(event) => event,
+            Err(e) => {
+                error!(LOG, "Invalid status notification: {:?}", e);
+                continue;
+            }
+        };
+
+        // Sending only fails while no stream is open, which is fine
+        let _ = relayer_state.status_events.send(event);
+    }
+}
+
+/// Parses the payload of a notification on the status channel.
+fn parse_notification(payload: &str) -> Result<RequestStatusEvent> {
+    let notification: StatusNotification = serde_json::from_str(payload)?;
+    Ok(RequestStatusEvent {
+        id: notification.id,
+        status: notification.status.parse()?,
+        error_code: notification.error_code,
+    })
+}
+
+/// Streams the status changes of a request as server-sent events.
+///
+/// The stream starts with the current status and ends after the request reaches a terminal
+/// status or the client disconnects.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `events` - A subscription to the status broadcast, taken before `current` was read.
+/// * `current` - The current status of the request.
+///
+/// # Returns
+///
+/// The stream of `status` events.
+pub fn stream_status(
+    relayer_state: Arc<RelayerState>,
+    mut events: broadcast::Receiver<RequestStatusEvent>,
+    current: RequestStatusEvent,
+) -> ReceiverStream<Result<Event, Infallible>> {
+    let (tx, rx) = mpsc::channel(16);
+
+    tokio::spawn(async move {
+        let request_id = current.id;
+        let mut next = Some(current);
+        while let Some(status_event) = next.take() {
+            let sse_event = match Event::default().event("status").json_data(&status_event) {
+                Ok(sse_event) => sse_event,
+                Err(e) => {
+                    error!(LOG, "Failed to serialize status event: {:?}", e);
+                    return;
+                }
+            };
+            if tx.send(Ok(sse_event)).await.is_err() || status_event.status.is_terminal() {
+                return;
+            }
+
+            next = tokio::select! {
+                event = next_event(&relayer_state, &mut events, request_id) => event,
+                _ = tx.closed() => None,
+            };
+        }
+    });
+
+    ReceiverStream::new(rx)
+}
+
+/// Waits for the next status change of a request.
+async fn next_event(
+    relayer_state: &RelayerState,
+    events: &mut broadcast::Receiver<RequestStatusEvent>,
+    request_id: Uuid,
+) -> Option<RequestStatusEvent> {
+    loop {
+        match events.recv().await {
+            Ok(event) if event.id == request_id => return Some(event),
+            Ok(_) => continue,
+            // Changes were dropped while the stream lagged behind, so report the current status
+            Err(RecvError::Lagged(_)) => {
+                let request = get_request(&relayer_state.db, request_id).await.ok()?;
+                return Some(RequestStatusEvent::from_request(&request));
+            }
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
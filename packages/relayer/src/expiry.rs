@@ -0,0 +1,104 @@
+//! Reminders and expiry of requests waiting for a reply.
+//!
+//! Requests in `EmailSent` get a reminder email shortly before they expire and are moved to
+//! `Expired` once their TTL has passed. Both steps claim the requests in a single update, so
+//! several relayer replicas can run the scheduler without sending an email twice.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use relayer_utils::LOG;
+use slog::{error, info};
+
+use crate::{
+    command::{get_account_code_for_email, parse_command_template},
+    mail::{handle_email_event, EmailEvent},
+    model::{claim_request_reminders, expire_requests, ErrorCode, RequestStatus},
+    prove::is_legacy_request,
+    webhook::notify_status,
+    RelayerState,
+};
+
+/// Runs the scheduler that reminds and expires requests until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_expiry_scheduler(relayer_state: RelayerState) {
+    let poll_interval = Duration::from_secs(relayer_state.config.expiry.poll_interval_secs.max(1));
+
+    info!(LOG, "Expiry scheduler started");
+    loop {
+        if let Err(e) = send_reminders(&relayer_state).await {
+            error!(LOG, "Failed to send request reminders: {:?}", e);
+        }
+        if let Err(e) = expire(&relayer_state).await {
+            error!(LOG, "Failed to expire requests: {:?}", e);
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// Sends a reminder email for every request that is about to expire.
+async fn send_reminders(relayer_state: &RelayerState) -> Result<()> {
+    let Some(reminder_before_secs) = relayer_state.config.expiry.reminder_before_secs else {
+        return Ok(());
+    };
+
+    for request in claim_request_reminders(&relayer_state.db, reminder_before_secs).await? {
+        info!(LOG, "Sending reminder for request {}", request.id);
+
+        let email_tx_auth = &request.email_tx_auth;
+        let event = EmailEvent::Reminder {
+            request_id: request.id,
+            email_address: email_tx_auth.email_address.clone(),
+            command: parse_command_template(
+                &email_tx_auth.command_template,
+                email_tx_auth.command_params.clone(),
+            ),
+            account_code: get_account_code_for_email(email_tx_auth),
+            subject: format!("[Reminder] {}", email_tx_auth.subject),
+            body: email_tx_auth.body.clone(),
+            legacy: is_legacy_request(&relayer_state.config, email_tx_auth),
+            expires_at: request
+                .expires_at
+                .map(|expires_at| expires_at.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default(),
+        };
+
+        // A reminder is best effort, the request still expires if it cannot be sent
+        if let Err(e) = handle_email_event(event, relayer_state.clone()).await {
+            error!(
+                LOG,
+                "Failed to send reminder for request {}: {:?}", request.id, e
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Expires the requests past their TTL and tells their recipients.
+async fn expire(relayer_state: &RelayerState) -> Result<()> {
+    for request in expire_requests(&relayer_state.db).await? {
+        info!(LOG, "Request {} expired", request.id);
+        notify_status(relayer_state, request.id, RequestStatus::Expired).await;
+
+        let email_tx_auth = &request.email_tx_auth;
+        let event = EmailEvent::Error {
+            email_addr: email_tx_auth.email_address.clone(),
+            error: format!(
+                "{}. Replies to this request are no longer accepted.",
+                ErrorCode::Expired
+            ),
+            original_subject: email_tx_auth.subject.clone(),
+            original_message_id: None,
+        };
+        if let Err(e) = handle_email_event(event, relayer_state.clone()).await {
+            error!(
+                LOG,
+                "Failed to send expiry email for request {}: {:?}", request.id, e
+            );
+        }
+    }
+    Ok(())
+}
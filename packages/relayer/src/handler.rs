@@ -2,24 +2,43 @@ use std::sync::Arc;
 
 use anyhow::Result;
 use axum::{
-    extract::State,
+    extract::{Path, State},
     http::{request, StatusCode},
-    response::IntoResponse,
-    Json,
+    response::{
+        sse::{KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Extension, Json,
 };
+use ethers::abi::AbiEncode;
 use regex::Regex;
-use relayer_utils::{field_to_hex, ParsedEmail, LOG};
+use relayer_utils::{ParsedEmail, LOG};
 use serde_json::{json, Value};
 use slog::{error, info, trace};
 use uuid::Uuid;
 
 use crate::{
     abis::EmailAuthMsg,
-    command::parse_command_template,
-    constants::REQUEST_ID_REGEX,
-    mail::{handle_email, handle_email_event, EmailEvent},
-    model::{create_request, get_request, update_request, RequestStatus},
-    schema::{AccountSaltSchema, EmailTxAuthSchema},
+    auth::{generate_api_key, hash_api_key, issue_stream_token},
+    chain::parse_controller_function,
+    command::{get_account_code_for_email, parse_command_template},
+    config::Config,
+    constants::{MAX_BATCH_SIZE, REQUEST_ID_REGEX},
+    dkim::verify_dkim_signature,
+    events::{stream_status, RequestStatusEvent},
+    mail::{get_reply_message_ids, handle_email_event, is_same_email_address, EmailEvent},
+    model::{
+        create_request, create_request_group, fail_request, get_api_clients, get_email_auth_msg,
+        get_expected_reply, get_group_requests, get_jobs, get_on_chain_transaction,
+        get_reply_message_id, get_request, get_request_group, get_revoked_dkim_keys,
+        get_webhook_deliveries, insert_api_client, mark_expected_reply, release_expected_reply,
+        revoke_api_client, update_request, ApiClientModel, ErrorCode, RequestModel, RequestStatus,
+    },
+    prove::{get_circuit_profile, is_legacy_request, RecipientCommitment},
+    queue::{enqueue, Job},
+    rate_limit::check_submit_rate_limits,
+    schema::{AccountSaltSchema, CreateApiKeySchema, EmailTxAuthSchema, SubmitBatchSchema},
+    webhook::{check_callback_url, notify_status},
     RelayerState,
 };
 use serde::Serialize;
@@ -32,21 +51,48 @@ use sqlx::PgPool;
 impl EmailAuthMsg {
     /// Saves the email authentication message to the database.
     ///
+    /// A message whose email nullifier is already stored is skipped, so processing the same
+    /// email twice leaves a single row. The ABI-encoded message is stored as well, so it can be
+    /// reused instead of proving the email again.
+    ///
     /// # Arguments
     /// * `pool` - PostgreSQL connection pool
     /// * `request_id` - Unique identifier for the request
+    /// * `message_id` - Message-ID of the email the message was generated from
+    /// * `recipient` - Recipient commitment of a recipient-bound proof, stored alongside the message
     ///
     /// # Returns
-    /// * `Result<()>` - Success or error during database operation
-    pub async fn save(&self, pool: &PgPool, request_id: Uuid) -> Result<()> {
-        sqlx::query!(
-            "INSERT INTO email_auth_messages (request_id, response) VALUES ($1, $2)",
+    /// * `Result<bool>` - Whether the message was stored, `false` if its nullifier already was
+    pub async fn save(
+        &self,
+        pool: &PgPool,
+        request_id: Uuid,
+        message_id: Option<String>,
+        recipient: Option<&RecipientCommitment>,
+    ) -> Result<bool> {
+        let mut response = serde_json::to_value(self)?;
+        if let Some(recipient) = recipient {
+            response["recipient"] = serde_json::to_value(recipient)?;
+        }
+
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO email_auth_messages (request_id, response, message_id, email_nullifier, encoded_msg)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (email_nullifier) DO NOTHING
+            "#,
             request_id.to_string(),
-            serde_json::to_value(self)?
+            response,
+            message_id,
+            format!(
+                "0x{}",
+                ethers::utils::hex::encode(self.proof.email_nullifier)
+            ),
+            self.clone().encode()
         )
         .execute(pool)
         .await?;
-        Ok(())
+        Ok(query_result.rows_affected() > 0)
     }
 }
 
@@ -133,68 +179,75 @@ pub async fn health_checker_handler() -> impl IntoResponse {
 /// # Arguments
 ///
 /// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
 /// * `body` - The JSON body of the request, deserialized into an `EmailTxAuthSchema`.
 ///
 /// # Returns
 ///
 /// A `Result` containing:
-/// - `Ok`: A JSON response with a success status, message, and request ID.
+/// - `Ok`: A JSON response with a success status, message, and request ID, or a 429 response
+///   with a `Retry-After` header if a rate limit is exceeded.
 /// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
 pub async fn submit_handler(
     State(relayer_state): State<Arc<RelayerState>>,
+    api_client: Option<Extension<ApiClientModel>>,
     Json(body): Json<EmailTxAuthSchema>,
-) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+) -> Result<Response, (StatusCode, Json<Value>)> {
     // Log the received payload
     info!(LOG, "Payload: {:?}", body);
 
-    // Create a new request in the database and obtain a UUID
-    let uuid = create_request(&relayer_state.db, &body)
-        .await
-        .map_err(|e| {
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(json!({"error": e.to_string()})),
-            )
-        })?;
-
-    // Log the created request ID
-    info!(LOG, "Request ID created: {}", uuid);
-
-    // Parse the command template with the provided parameters
-    let command = parse_command_template(&body.command_template, body.command_params);
+    // Reject invalid on-chain submissions, callback URLs and TTLs
+    validate_submission(&relayer_state.config, &body).await?;
 
-    // Log the parsed command
-    info!(LOG, "Command: {:?}", command);
+    // Reject the request if the client or the recipient has sent too many requests
+    let api_client = api_client.map(|Extension(api_client)| api_client);
+    if let Some(exceeded) = check_submit_rate_limits(
+        &relayer_state,
+        api_client.as_ref(),
+        &[body.email_address.as_str()],
+    )
+    .await
+    .map_err(|e| {
+        (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })? {
+        return Ok(exceeded.into_response());
+    }
 
-    // Determine the account code if it exists in the email
-    let account_code = if body.code_exists_in_email {
-        let hex_code = field_to_hex(&body.account_code.0);
-        Some(hex_code.trim_start_matches("0x").to_string())
-    } else {
-        None
-    };
+    let ttl_secs = body
+        .ttl_secs
+        .unwrap_or(relayer_state.config.expiry.default_ttl_secs);
 
-    // Handle the email event by sending a command email
-    handle_email_event(
-        EmailEvent::Command {
-            request_id: uuid,
-            email_address: body.email_address.clone(),
-            command,
-            account_code,
-            subject: format!("[Reply Needed] {}", body.subject.clone()),
-            body: body.body.clone(),
-        },
-        (*relayer_state).clone(),
+    // Create a new request in the database and obtain a UUID
+    let uuid = create_request(
+        &relayer_state.db,
+        &body,
+        ttl_secs,
+        api_client.as_ref().map(|api_client| api_client.id),
     )
     .await
     .map_err(|e| {
-        // Convert the error to the desired type
         (
-            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
             axum::Json(json!({"error": e.to_string()})),
         )
     })?;
 
+    // Log the created request ID
+    info!(LOG, "Request ID created: {}", uuid);
+
+    // Send the command email to the recipient
+    send_command_email(&relayer_state, uuid, &body)
+        .await
+        .map_err(|error_message| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": error_message})),
+            )
+        })?;
+
     // Create a JSON response indicating success
     let response = json!({
         "status": "success",
@@ -203,13 +256,12 @@ pub async fn submit_handler(
     });
 
     // Return the success response
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 /// Handles the reception of an email and processes it accordingly.
 ///
-/// This asynchronous handler function extracts the request ID from the email body, updates the request status,
-/// parses the email, and sends an acknowledgment. It processes the email and handles any resulting events.
+/// This asynchronous handler function passes the raw email to `process_reply_email`.
 ///
 /// # Arguments
 ///
@@ -225,79 +277,251 @@ pub async fn receive_email_handler(
     State(relayer_state): State<Arc<RelayerState>>,
     body: String,
 ) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    // Define the regex pattern for UUID
-    let uuid_regex = Regex::new(REQUEST_ID_REGEX).unwrap();
+    let response = process_reply_email(relayer_state, body).await?;
+    Ok((StatusCode::OK, Json(response)))
+}
 
-    // Attempt to find a UUID in the body
-    let captures = uuid_regex.captures(&body);
+/// Processes a reply email, however it reached the relayer.
+///
+/// This function finds the request the email replies to, either through the
+/// `In-Reply-To`/`References` headers or the request ID in the email body, and parses the email.
+/// It checks that the email was sent from the request's email address and is the first reply to the
+/// request, updates the request status, and sends an acknowledgment. The email is then queued for
+/// proving, which happens in the background job workers.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `body` - The raw email as a `String`.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON value with a success status and message.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn process_reply_email(
+    relayer_state: Arc<RelayerState>,
+    body: String,
+) -> Result<Value, (StatusCode, Json<Value>)> {
+    // Correlate the reply with the command email it answers
+    let message_ids = get_reply_message_ids(&body);
+    let expected_reply = get_expected_reply(&relayer_state.db, &message_ids)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
 
-    let request_id = captures
-        .and_then(|caps| caps.get(2).map(|m| m.as_str()))
-        .ok_or_else(|| {
+    let request_id = match expected_reply.and_then(|reply| reply.request_id) {
+        Some(id) => id.parse::<Uuid>().map_err(|_| {
             (
                 reqwest::StatusCode::BAD_REQUEST,
-                axum::Json(json!({"error": "Request ID is None"})),
+                axum::Json(json!({"error": "Failed to parse request ID"})),
             )
-        })
-        .and_then(|id| {
-            id.parse::<Uuid>().map_err(|_| {
-                (
-                    reqwest::StatusCode::BAD_REQUEST,
-                    axum::Json(json!({"error": "Failed to parse request ID"})),
-                )
-            })
-        })?;
+        })?,
+        // Fall back to the request ID quoted in the email body
+        None => get_request_id_from_body(&body)?,
+    };
 
     info!(LOG, "Request ID received: {}", request_id);
 
-    // Update the request status in the database
-    update_request(
+    // Log the received body
+    info!(LOG, "Received email body: {:?}", body);
+
+    // Parse the email from the raw body. The request is left untouched until the sender is
+    // verified, so a stranger who knows the request ID cannot end it.
+    let parsed_email = ParsedEmail::new_from_raw_email(&body).await.map_err(|e| {
+        (
+            reqwest::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // Reject emails whose DKIM signature does not verify before any proving work. No error email
+    // is sent, as the sender's address cannot be trusted.
+    if let Err(e) = verify_dkim_signature(&parsed_email) {
+        error!(
+            LOG,
+            "Reply to request {} failed DKIM verification: {:?}", request_id, e
+        );
+        return Err((
+            reqwest::StatusCode::BAD_REQUEST,
+            axum::Json(json!({
+                "error": format!("{}: {}", ErrorCode::DkimVerificationFailed, e),
+                "code": ErrorCode::DkimVerificationFailed.as_str(),
+            })),
+        ));
+    }
+
+    // Extract the sender's address
+    let from_addr = parsed_email.get_from_addr().map_err(|e| {
+        (
+            reqwest::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // Extract the original subject of the email
+    let original_subject = parsed_email.get_subject_all().map_err(|e| {
+        (
+            reqwest::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // Retrieve the request from the database
+    let request = get_request(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| {
+            // Convert the error to the expected type
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    // Only the address the command email was sent to may reply to it. The reply is rejected and
+    // the request keeps waiting for its owner, so a stranger cannot end it.
+    if !is_same_email_address(&from_addr, &request.email_tx_auth.email_address) {
+        error!(
+            LOG,
+            "Reply to request {} from {} does not match the request's email address",
+            request_id,
+            from_addr
+        );
+        if relayer_state.config.notify_sender_mismatch {
+            notify_sender_mismatch(&relayer_state, &request, &from_addr).await;
+        }
+        return Err((
+            reqwest::StatusCode::FORBIDDEN,
+            axum::Json(json!({
+                "error": "Sender does not match the request's email address",
+                "code": ErrorCode::SenderMismatch.as_str(),
+                "status": RequestStatus::Rejected,
+            })),
+        ));
+    }
+
+    // Replies that arrive after the request expired cannot approve it anymore
+    if is_expired(&request) {
+        error!(LOG, "Reply to expired request {} rejected", request_id);
+        let error = format!(
+            "This request expired{} and can no longer be approved. \
+            Please ask for a new request.",
+            request
+                .expires_at
+                .map(|expires_at| format!(" on {} UTC", expires_at.format("%Y-%m-%d %H:%M")))
+                .unwrap_or_default()
+        );
+        if let Err(e) = handle_email_event(
+            EmailEvent::Error {
+                email_addr: from_addr.clone(),
+                error,
+                original_subject,
+                original_message_id: parsed_email.get_message_id().ok(),
+            },
+            (*relayer_state).clone(),
+        )
+        .await
+        {
+            error!(LOG, "Error handling email event: {:?}", e);
+        }
+        return Err((
+            reqwest::StatusCode::GONE,
+            axum::Json(json!({
+                "error": ErrorCode::Expired.to_string(),
+                "code": ErrorCode::Expired.as_str(),
+            })),
+        ));
+    }
+
+    // Only the first reply to the command email is processed
+    let message_id = parsed_email.get_message_id().ok();
+    let is_first_reply = mark_expected_reply(
         &relayer_state.db,
-        request_id,
-        RequestStatus::EmailResponseReceived,
+        &request_id.to_string(),
+        message_id.as_deref(),
     )
     .await
     .map_err(|e| {
-        // Convert the error to the expected type
         (
             reqwest::StatusCode::INTERNAL_SERVER_ERROR,
             axum::Json(json!({"error": e.to_string()})),
         )
     })?;
+    if !is_first_reply {
+        // A redelivery of the reply that was already received is acknowledged without
+        // processing it again, along with the message generated for it so far
+        let reply_message_id = get_reply_message_id(&relayer_state.db, &request_id.to_string())
+            .await
+            .map_err(|e| {
+                (
+                    reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(json!({"error": e.to_string()})),
+                )
+            })?;
+        if message_id.is_some() && reply_message_id == message_id {
+            info!(LOG, "Reply to request {} was already received", request_id);
+            let email_auth_msg = get_email_auth_msg(&relayer_state.db, request_id)
+                .await
+                .map_err(|e| {
+                    (
+                        reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                        axum::Json(json!({"error": e.to_string()})),
+                    )
+                })?;
+            let response = json!({
+                "status": "success",
+                "message": "email already received",
+                "id": request_id,
+                "requestStatus": request.status,
+                "emailAuthMsg": email_auth_msg,
+            });
+            return Ok(response);
+        }
 
-    // Log the received body
-    info!(LOG, "Received email body: {:?}", body);
+        return Err((
+            reqwest::StatusCode::CONFLICT,
+            axum::Json(json!({
+                "error": "A reply to this request was already received",
+                "code": ErrorCode::DuplicateReply.as_str(),
+                "status": RequestStatus::Rejected,
+            })),
+        ));
+    }
 
-    // Parse the email from the raw body
-    let parsed_email = ParsedEmail::new_from_raw_email(&body).await.map_err(|e| {
+    // Update the request status in the database
+    let updated = update_request(
+        &relayer_state.db,
+        request_id,
+        RequestStatus::EmailResponseReceived,
+    )
+    .await
+    .map_err(|e| {
         // Convert the error to the expected type
         (
             reqwest::StatusCode::INTERNAL_SERVER_ERROR,
             axum::Json(json!({"error": e.to_string()})),
         )
     })?;
-
-    // Extract the sender's address
-    let from_addr = match parsed_email.get_from_addr() {
-        Ok(addr) => addr,
-        Err(e) => {
-            return Err((
-                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(json!({"error": e.to_string()})),
-            ))
-        }
-    };
-
-    // Extract the original subject of the email
-    let original_subject = match parsed_email.get_subject_all() {
-        Ok(subject) => subject,
-        Err(e) => {
-            return Err((
-                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(json!({"error": e.to_string()})),
-            ))
-        }
-    };
+    if !updated {
+        info!(
+            LOG,
+            "Reply to request {} arrived after it ended", request_id
+        );
+        return Err((
+            reqwest::StatusCode::CONFLICT,
+            axum::Json(json!({"error": "The request has already ended"})),
+        ));
+    }
+    notify_status(
+        &relayer_state,
+        request_id,
+        RequestStatus::EmailResponseReceived,
+    )
+    .await;
 
     // Send acknowledgment email
     match handle_email_event(
@@ -319,117 +543,684 @@ pub async fn receive_email_handler(
         }
     }
 
-    // Retrieve the request from the database
-    let request = get_request(&relayer_state.db, request_id)
-        .await
-        .map_err(|e| {
-            // Convert the error to the expected type
-            (
-                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
-                axum::Json(json!({"error": e.to_string()})),
-            )
-        })?;
-
-    // Process the email
-    match handle_email(body, request, (*relayer_state).clone()).await {
-        Ok(event) => match handle_email_event(event, (*relayer_state).clone()).await {
-            Ok(_) => {}
-            Err(e) => {
-                error!(LOG, "Error handling email event: {:?}", e);
-            }
-        },
-        Err(e) => {
-            error!(LOG, "Error handling email: {:?}", e);
-            let original_subject = parsed_email
-                .get_subject_all()
-                .unwrap_or("Unknown Error".to_string());
-            match handle_email_event(
-                EmailEvent::Error {
-                    email_addr: from_addr,
-                    error: e.to_string(),
-                    original_subject,
-                    original_message_id: parsed_email.get_message_id().ok(),
-                },
-                (*relayer_state).clone(),
-            )
-            .await
-            {
-                Ok(_) => {}
-                Err(e) => {
-                    error!(LOG, "Error handling email event: {:?}", e);
-                }
-            }
+    // Queue the email for proving, the job workers process it from here
+    if let Err(e) = enqueue(
+        &relayer_state,
+        request_id,
+        Job::ProcessReply { email: body },
+    )
+    .await
+    {
+        // The reply is not processed, so the request waits for the user to reply again
+        if let Err(e) = release_expected_reply(&relayer_state.db, &request_id.to_string()).await {
+            error!(
+                LOG,
+                "Failed to release the reply of request {}: {:?}", request_id, e
+            );
         }
+        match update_request(&relayer_state.db, request_id, RequestStatus::EmailSent).await {
+            Ok(true) => notify_status(&relayer_state, request_id, RequestStatus::EmailSent).await,
+            Ok(false) => {}
+            Err(e) => error!(LOG, "Failed to update request {}: {:?}", request_id, e),
+        }
+        return Err((
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({"error": e.to_string()})),
+        ));
     }
 
     // Create a JSON response indicating success
     let response = json!({
         "status": "success",
         "message": "email received",
+        "id": request_id,
     });
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok(response)
 }
 
-/// Retrieves the status of a specific request based on its ID.
+/// Extracts the request ID quoted in the body of a reply email.
 ///
-/// This asynchronous handler function extracts the request ID from the URI, retrieves the request
-/// from the database, and returns its status in a JSON response.
+/// # Arguments
+///
+/// * `body` - The raw email.
+///
+/// # Returns
+///
+/// A `Result` containing the request ID, or the error response to return if none is found.
+fn get_request_id_from_body(body: &str) -> Result<Uuid, (StatusCode, Json<Value>)> {
+    // Define the regex pattern for UUID
+    let uuid_regex = Regex::new(REQUEST_ID_REGEX).unwrap();
+
+    // Attempt to find a UUID in the body
+    let captures = uuid_regex.captures(body);
+
+    captures
+        .and_then(|caps| caps.get(2).map(|m| m.as_str()))
+        .ok_or_else(|| {
+            (
+                reqwest::StatusCode::BAD_REQUEST,
+                axum::Json(json!({"error": "Request ID is None"})),
+            )
+        })
+        .and_then(|id| {
+            id.parse::<Uuid>().map_err(|_| {
+                (
+                    reqwest::StatusCode::BAD_REQUEST,
+                    axum::Json(json!({"error": "Failed to parse request ID"})),
+                )
+            })
+        })
+}
+
+/// Streams the status of a request as server-sent events.
+///
+/// The stream starts with the current status, sends a `status` event on every transition and
+/// ends once the request is finished, failed, expired or rejected.
 ///
 /// # Arguments
 ///
 /// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
-/// * `request` - The parts of the HTTP request, used to extract the request ID from the URI.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
+/// * `request_id` - The unique identifier of the request, taken from the path.
 ///
 /// # Returns
 ///
 /// A `Result` containing:
-/// - `Ok`: A JSON response with the request status and details.
+/// - `Ok`: An SSE stream of status events.
 /// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
-pub async fn get_status_handler(
+pub async fn get_status_stream_handler(
     State(relayer_state): State<Arc<RelayerState>>,
-    request: request::Parts,
+    api_client: Option<Extension<ApiClientModel>>,
+    Path(request_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
-    // Extract the request ID from the URI path
-    let request_id = request
-        .uri
-        .path()
-        .trim_start_matches("/api/status/")
-        .parse::<Uuid>()
-        .map_err(|_| {
-            (
-                reqwest::StatusCode::BAD_REQUEST,
-                axum::Json(json!({"error": "Failed to parse request ID"})),
-            )
-        })?;
+    // Subscribe before reading the current status, so no transition in between is missed
+    let events = relayer_state.status_events.subscribe();
 
-    // Retrieve the request from the database using the request ID
     let request = get_request(&relayer_state.db, request_id)
         .await
-        .map_err(|e| {
-            (
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => request_not_found(),
+            e => (
                 reqwest::StatusCode::INTERNAL_SERVER_ERROR,
                 axum::Json(json!({"error": e.to_string()})),
-            )
+            ),
         })?;
+    if !is_accessible(api_client.as_deref(), request.client_id) {
+        return Err(request_not_found());
+    }
+    let current = RequestStatusEvent::from_request(&request);
 
-    let email_auth_msg = sqlx::query!(
-        "SELECT response FROM email_auth_messages WHERE request_id = $1",
-        request_id.to_string()
-    )
-    .fetch_optional(&relayer_state.db)
-    .await
-    .map_err(|e| {
-        (
-            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
-            axum::Json(json!({"error": e.to_string()})),
-        )
-    })?;
-    let response = json!({
-        "message": "request status",
-        "request": request,
-        "response": email_auth_msg.map(|msg| msg.response),
-    });
+    let stream = stream_status(relayer_state.clone(), events, current);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Issues a short-lived token for the status stream of a request.
+///
+/// The token is passed as the `token` query parameter of `/api/status/:id/stream` by clients
+/// that cannot send an `Authorization` header, such as a browser's `EventSource`.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
+/// * `request_id` - The unique identifier of the request, taken from the path.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the token and the Unix timestamp it expires at.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn create_stream_token_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    api_client: Option<Extension<ApiClientModel>>,
+    Path(request_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let Some(Extension(api_client)) = api_client else {
+        return Err((
+            reqwest::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": "The status stream needs no token while auth is disabled"})),
+        ));
+    };
+
+    let request = get_request(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => request_not_found(),
+            e => (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            ),
+        })?;
+    if !is_accessible(Some(&api_client), request.client_id) {
+        return Err(request_not_found());
+    }
+
+    // The config validation requires the secret while auth is enabled
+    let secret = relayer_state
+        .config
+        .auth
+        .stream_token_secret
+        .as_deref()
+        .unwrap_or_default();
+    let (token, expires_at) = issue_stream_token(secret, &api_client, request_id);
+
+    Ok(Json(json!({
+        "token": token,
+        "expiresAt": expires_at,
+    })))
+}
+
+/// Submits a group of email transaction authentication requests.
+///
+/// This asynchronous handler function validates every request of the batch, creates them together
+/// with their group in a single transaction, and sends all command emails. The group is complete
+/// once the replies to `threshold` of its requests are proven.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
+/// * `body` - The JSON body of the request, deserialized into a `SubmitBatchSchema`.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the group ID and the outcome of each command email, with a 500
+///   status if no email could be sent, or a 429 response with a `Retry-After` header if a rate
+///   limit is exceeded.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn submit_batch_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    api_client: Option<Extension<ApiClientModel>>,
+    Json(body): Json<SubmitBatchSchema>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    info!(LOG, "Batch payload with {} requests", body.requests.len());
+
+    if body.requests.is_empty() || body.requests.len() > MAX_BATCH_SIZE {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({
+                "error": format!("A batch must contain between 1 and {} requests", MAX_BATCH_SIZE)
+            })),
+        ));
+    }
+
+    let threshold = body.threshold.unwrap_or(body.requests.len());
+    if threshold == 0 || threshold > body.requests.len() {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({
+                "error": "threshold must be between 1 and the number of requests"
+            })),
+        ));
+    }
+
+    for request in &body.requests {
+        validate_submission(&relayer_state.config, request).await?;
+    }
+
+    // Reject the whole batch if the client or any of its recipients has sent too many requests
+    let api_client = api_client.map(|Extension(api_client)| api_client);
+    let email_addresses: Vec<&str> = body
+        .requests
+        .iter()
+        .map(|request| request.email_address.as_str())
+        .collect();
+    if let Some(exceeded) =
+        check_submit_rate_limits(&relayer_state, api_client.as_ref(), &email_addresses)
+            .await
+            .map_err(|e| {
+                (
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    axum::Json(json!({"error": e.to_string()})),
+                )
+            })?
+    {
+        return Ok(exceeded.into_response());
+    }
+
+    // Create the group and all of its requests atomically
+    let (group_id, request_ids) = create_request_group(
+        &relayer_state.db,
+        &body.requests,
+        relayer_state.config.expiry.default_ttl_secs,
+        threshold as i32,
+        api_client.as_ref().map(|api_client| api_client.id),
+    )
+    .await
+    .map_err(|e| {
+        (
+            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    info!(
+        LOG,
+        "Request group {} created with {} requests",
+        group_id,
+        request_ids.len()
+    );
+
+    // A failed email only fails its own request, the rest of the group can still reach the threshold
+    let mut requests = vec![];
+    let mut sent = 0;
+    for (request_id, request) in request_ids.into_iter().zip(&body.requests) {
+        let outcome = match send_command_email(&relayer_state, request_id, request).await {
+            Ok(()) => {
+                sent += 1;
+                json!({"id": request_id, "status": "success", "message": "email sent"})
+            }
+            Err(error_message) => {
+                json!({"id": request_id, "status": "error", "error": error_message})
+            }
+        };
+        requests.push(outcome);
+    }
+
+    let (status_code, status, message) = if sent == requests.len() {
+        (StatusCode::OK, "success", "emails sent".to_string())
+    } else if sent > 0 {
+        (
+            StatusCode::OK,
+            "partial",
+            format!("{} of {} emails sent", sent, requests.len()),
+        )
+    } else {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "error",
+            "no email could be sent".to_string(),
+        )
+    };
+    let response = json!({
+        "status": status,
+        "message": message,
+        "groupId": group_id,
+        "threshold": threshold,
+        "thresholdReachable": sent >= threshold,
+        "requests": requests,
+    });
+
+    Ok((status_code, Json(response)).into_response())
+}
+
+/// Retrieves the status of a group of requests.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
+/// * `group_id` - The unique identifier of the group, taken from the path.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the group, its requests and whether the threshold is reached.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn get_group_status_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    api_client: Option<Extension<ApiClientModel>>,
+    Path(group_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let group = get_request_group(&relayer_state.db, group_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?
+        .filter(|group| is_accessible(api_client.as_deref(), group.client_id))
+        .ok_or_else(|| {
+            (
+                reqwest::StatusCode::NOT_FOUND,
+                axum::Json(json!({"error": "Request group not found"})),
+            )
+        })?;
+
+    let requests = get_group_requests(&relayer_state.db, group_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    let proven = requests.iter().filter(|request| request.proven).count();
+    let response = json!({
+        "message": "group status",
+        "group": group,
+        "proven": proven,
+        "thresholdReached": proven >= group.threshold as usize,
+        "requests": requests,
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Validates a submitted request before it is created.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the relayer.
+/// * `body` - The submitted `EmailTxAuthSchema`.
+///
+/// # Returns
+///
+/// A `Result` containing a 400 error if the request is invalid.
+async fn validate_submission(
+    config: &Config,
+    body: &EmailTxAuthSchema,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    // Reject on-chain submissions whose function signature cannot be parsed
+    if let Some(signature) = body
+        .on_chain
+        .as_ref()
+        .and_then(|on_chain| on_chain.function_signature.as_ref())
+    {
+        parse_controller_function(signature).map_err(|e| {
+            (
+                axum::http::StatusCode::BAD_REQUEST,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+    }
+
+    // Reject callback URLs the relayer cannot or must not post to
+    if let Some(callback_url) = &body.callback_url {
+        check_callback_url(&config.webhook, callback_url)
+            .await
+            .map_err(|e| {
+                (
+                    axum::http::StatusCode::BAD_REQUEST,
+                    axum::Json(json!({"error": format!("Invalid callbackUrl: {}", e)})),
+                )
+            })?;
+    }
+
+    // Reject circuit profiles the relayer cannot prove with
+    let profile = get_circuit_profile(config, body.circuit_profile.as_deref()).map_err(|e| {
+        (
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+    if profile.legacy && body.recipient_bound {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({
+                "error": "recipientBound is not supported by legacy circuit profiles"
+            })),
+        ));
+    }
+    // The on-chain verifier checks `email_auth` proofs, whose public signals have no recipient
+    if body.recipient_bound && body.on_chain.is_some() {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": "recipientBound cannot be combined with onChain"})),
+        ));
+    }
+
+    // Reject a TTL that would expire the request before the email is answered
+    if body.ttl_secs == Some(0) {
+        return Err((
+            axum::http::StatusCode::BAD_REQUEST,
+            axum::Json(json!({"error": "ttlSecs must be greater than zero"})),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Sends the command email of a request, failing the request if the email cannot be sent.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer.
+/// * `request_id` - The unique identifier of the request.
+/// * `body` - The `EmailTxAuthSchema` of the request.
+///
+/// # Returns
+///
+/// A `Result` containing the error message if the email could not be sent.
+async fn send_command_email(
+    relayer_state: &RelayerState,
+    request_id: Uuid,
+    body: &EmailTxAuthSchema,
+) -> Result<(), String> {
+    // Parse the command template with the provided parameters
+    let command = parse_command_template(&body.command_template, body.command_params.clone());
+
+    // Log the parsed command
+    info!(LOG, "Command: {:?}", command);
+
+    // Determine the account code if it exists in the email
+    let account_code = get_account_code_for_email(body);
+
+    // Handle the email event by sending a command email
+    if let Err(e) = handle_email_event(
+        EmailEvent::Command {
+            request_id,
+            email_address: body.email_address.clone(),
+            command,
+            account_code,
+            subject: format!("[Reply Needed] {}", body.subject.clone()),
+            body: body.body.clone(),
+            legacy: is_legacy_request(&relayer_state.config, body),
+        },
+        relayer_state.clone(),
+    )
+    .await
+    {
+        // The command email never reached the user, so the request cannot be fulfilled
+        let error_message = format!("{:#}", e);
+        fail(
+            relayer_state,
+            request_id,
+            RequestStatus::Failed,
+            ErrorCode::of(&e),
+            &error_message,
+        )
+        .await;
+        return Err(error_message);
+    }
+
+    Ok(())
+}
+
+/// Checks whether a request no longer accepts replies because it expired.
+///
+/// A request past its expiry counts as expired even before the scheduler has updated its status.
+fn is_expired(request: &RequestModel) -> bool {
+    if request.status == RequestStatus::Expired {
+        return true;
+    }
+    request.status == RequestStatus::EmailSent
+        && request
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= chrono::Utc::now().naive_utc())
+}
+
+/// Moves a request to a terminal failure state and notifies its callback, logging if the update
+/// itself fails.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer.
+/// * `request_id` - The unique identifier of the request.
+/// * `status` - The failure status to set for the request.
+/// * `error_code` - The code of the error that ended the request.
+/// * `error_message` - The message of the error that ended the request.
+async fn fail(
+    relayer_state: &RelayerState,
+    request_id: Uuid,
+    status: RequestStatus,
+    error_code: ErrorCode,
+    error_message: &str,
+) {
+    match fail_request(
+        &relayer_state.db,
+        request_id,
+        status,
+        error_code,
+        error_message,
+    )
+    .await
+    {
+        Ok(true) => notify_status(relayer_state, request_id, status).await,
+        Ok(false) => info!(LOG, "Request {} has already ended", request_id),
+        Err(e) => error!(LOG, "Failed to update request {}: {:?}", request_id, e),
+    }
+}
+
+/// Checks whether the authenticated client may read a request or group it submitted.
+///
+/// # Arguments
+///
+/// * `api_client` - The authenticated API client, or `None` if auth is disabled.
+/// * `client_id` - The client that submitted the request or group.
+///
+/// # Returns
+///
+/// `true` if auth is disabled or the client submitted the record.
+fn is_accessible(api_client: Option<&ApiClientModel>, client_id: Option<Uuid>) -> bool {
+    match api_client {
+        Some(api_client) => client_id == Some(api_client.id),
+        None => true,
+    }
+}
+
+/// Builds the response for a request that does not exist or belongs to another client.
+fn request_not_found() -> (StatusCode, Json<Value>) {
+    (
+        reqwest::StatusCode::NOT_FOUND,
+        axum::Json(json!({"error": "Request not found"})),
+    )
+}
+
+/// Tells the owner of a request that a reply from another address was rejected.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer.
+/// * `request` - The request that received the reply.
+/// * `from_addr` - The address the rejected reply was sent from.
+async fn notify_sender_mismatch(
+    relayer_state: &RelayerState,
+    request: &RequestModel,
+    from_addr: &str,
+) {
+    let event = EmailEvent::Error {
+        email_addr: request.email_tx_auth.email_address.clone(),
+        error: format!(
+            "A reply to your request {} was sent from {}, which is not your email address. \
+            The reply was ignored and your request is still waiting for your reply.",
+            request.id, from_addr
+        ),
+        original_subject: request.email_tx_auth.subject.clone(),
+        original_message_id: None,
+    };
+    if let Err(e) = handle_email_event(event, relayer_state.clone()).await {
+        error!(LOG, "Error handling email event: {:?}", e);
+    }
+}
+
+/// Retrieves the status of a specific request based on its ID.
+///
+/// This asynchronous handler function extracts the request ID from the URI, retrieves the request
+/// from the database, and returns its status in a JSON response.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `request` - The parts of the HTTP request, used to extract the request ID from the URI.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the request status and details.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn get_status_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    request: request::Parts,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    // Extract the request ID from the URI path
+    let request_id = request
+        .uri
+        .path()
+        .trim_start_matches("/api/status/")
+        .parse::<Uuid>()
+        .map_err(|_| {
+            (
+                reqwest::StatusCode::BAD_REQUEST,
+                axum::Json(json!({"error": "Failed to parse request ID"})),
+            )
+        })?;
+    let api_client = request.extensions.get::<ApiClientModel>().cloned();
+
+    // Retrieve the request from the database using the request ID
+    let request = get_request(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => request_not_found(),
+            e => (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            ),
+        })?;
+    // Requests of other clients are reported as missing, so their IDs cannot be probed
+    if !is_accessible(api_client.as_ref(), request.client_id) {
+        return Err(request_not_found());
+    }
+
+    let email_auth_msg = get_email_auth_msg(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    // Retrieve the on-chain transaction, if the request submitted one
+    let transaction = get_on_chain_transaction(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    // Retrieve the background jobs processing the request
+    let jobs = get_jobs(&relayer_state.db, request_id).await.map_err(|e| {
+        (
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    // Retrieve the webhook delivery attempts of the request
+    let webhooks = get_webhook_deliveries(&relayer_state.db, request_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    let response = json!({
+        "message": "request status",
+        "request": request,
+        "response": email_auth_msg,
+        "transaction": transaction,
+        "jobs": jobs,
+        "webhooks": webhooks,
+    });
 
     // Return the success response
     Ok((StatusCode::OK, Json(response)))
@@ -451,3 +1242,151 @@ pub async fn account_salt_handler(
 
     Ok((StatusCode::OK, Json(response)))
 }
+
+/// Issues a new API key to a client.
+///
+/// The key is only returned in this response; the relayer stores its hash.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `body` - The JSON body of the request, deserialized into a `CreateApiKeySchema`.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the client ID and its API key.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn create_api_key_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    Json(body): Json<CreateApiKeySchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let api_key = generate_api_key();
+    let client_id = insert_api_client(&relayer_state.db, &body.name, &hash_api_key(&api_key))
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    info!(LOG, "API key issued to {} ({})", body.name, client_id);
+
+    let response = json!({
+        "id": client_id,
+        "name": body.name,
+        "apiKey": api_key,
+    });
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+/// Lists the API clients, without their keys.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the API clients.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn get_api_keys_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let api_clients = get_api_clients(&relayer_state.db).await.map_err(|e| {
+        (
+            reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(json!({"error": e.to_string()})),
+        )
+    })?;
+
+    Ok((StatusCode::OK, Json(json!({ "clients": api_clients }))))
+}
+
+/// Lists the registered DKIM keys that their domains no longer publish as registered.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response with the revoked keys.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn get_revoked_dkim_keys_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let keys = get_revoked_dkim_keys(&relayer_state.db)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    Ok((StatusCode::OK, Json(json!({ "keys": keys }))))
+}
+
+/// Lists the state of the prover services proofs are requested from, with their latency and
+/// failure statistics since the relayer started.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+///
+/// # Returns
+///
+/// A JSON response with the prover services.
+pub async fn get_provers_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        Json(json!({ "provers": relayer_state.prover_pool.status() })),
+    )
+}
+
+/// Revokes the API key of a client.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The state of the relayer, encapsulated in an `Arc` for thread-safe access.
+/// * `client_id` - The unique identifier of the client, taken from the path.
+///
+/// # Returns
+///
+/// A `Result` containing:
+/// - `Ok`: A JSON response confirming the revocation.
+/// - `Err`: A tuple with a `StatusCode` and a JSON error message if an error occurs.
+pub async fn revoke_api_key_handler(
+    State(relayer_state): State<Arc<RelayerState>>,
+    Path(client_id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<Value>)> {
+    let revoked = revoke_api_client(&relayer_state.db, client_id)
+        .await
+        .map_err(|e| {
+            (
+                reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                axum::Json(json!({"error": e.to_string()})),
+            )
+        })?;
+
+    if !revoked {
+        return Err((
+            reqwest::StatusCode::NOT_FOUND,
+            axum::Json(json!({"error": "API client not found or already revoked"})),
+        ));
+    }
+
+    info!(LOG, "API key of client {} revoked", client_id);
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({"status": "success", "message": "API key revoked"})),
+    ))
+}
@@ -0,0 +1,219 @@
+//! Ingestion of replies from an IMAP mailbox.
+//!
+//! When enabled, the relayer reads replies from the configured mailbox itself instead of waiting
+//! for them to be posted to `/api/receiveEmail`. New messages are noticed with IDLE where the
+//! server supports it and by polling otherwise. The UIDs of processed messages are recorded in
+//! the `imap_processed_messages` table, so a message is processed once even if it cannot be moved
+//! out of the mailbox or several relayer replicas read the same mailbox. Only unprocessed messages
+//! are fetched. A message that fails with an internal error is released and left in the mailbox,
+//! so it is processed again on the next pass.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use async_imap::Session;
+use futures::TryStreamExt;
+use relayer_utils::LOG;
+use slog::{error, info};
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{crypto::ring, pki_types::ServerName, ClientConfig, RootCertStore},
+    TlsConnector,
+};
+
+use crate::{
+    config::ImapConfig,
+    handler::process_reply_email,
+    model::{claim_imap_message, get_claimed_imap_uids, release_imap_message},
+    RelayerState,
+};
+
+/// The time after which IDLE is restarted, as servers may drop connections idle for 30 minutes.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(29 * 60);
+
+type ImapSession = Session<TlsStream<TcpStream>>;
+
+/// Reads replies from the IMAP mailbox until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_imap_ingestion(relayer_state: RelayerState) {
+    let imap_config = relayer_state.config.imap.clone();
+    let relayer_state = Arc::new(relayer_state);
+
+    loop {
+        if let Err(e) = ingest(&relayer_state, &imap_config).await {
+            error!(LOG, "IMAP ingestion failed: {:?}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(imap_config.poll_interval_secs.max(1))).await;
+    }
+}
+
+/// Processes the messages in the mailbox and waits for new ones until the connection fails.
+async fn ingest(relayer_state: &Arc<RelayerState>, imap_config: &ImapConfig) -> Result<()> {
+    let mut session = connect(imap_config).await?;
+    info!(
+        LOG,
+        "Reading replies from {} on {}", imap_config.mailbox, imap_config.host
+    );
+
+    let capabilities = session.capabilities().await?;
+    let use_idle = imap_config.idle && capabilities.has_str("IDLE");
+    let use_move = capabilities.has_str("MOVE");
+
+    loop {
+        let mailbox = session.select(&imap_config.mailbox).await?;
+        let uid_validity = mailbox.uid_validity.unwrap_or_default();
+
+        let mut uids = session
+            .uid_search("ALL")
+            .await?
+            .into_iter()
+            .collect::<Vec<_>>();
+        uids.sort_unstable();
+        let claimed_uids =
+            get_claimed_imap_uids(&relayer_state.db, &imap_config.mailbox, uid_validity, &uids)
+                .await?;
+        for uid in uids {
+            // Claimed messages were processed already and are only left to be moved
+            let processed = claimed_uids.contains(&uid)
+                || process_message(relayer_state, imap_config, &mut session, uid_validity, uid)
+                    .await?;
+            if !processed {
+                continue;
+            }
+            if let Some(processed_mailbox) = &imap_config.processed_mailbox {
+                move_message(&mut session, uid, processed_mailbox, use_move).await?;
+            }
+        }
+
+        if use_idle {
+            let mut idle = session.idle();
+            idle.init().await?;
+            {
+                // The wait is interrupted when the stop source is dropped, so keep it alive
+                let (wait, _interrupt) = idle.wait_with_timeout(IDLE_TIMEOUT);
+                wait.await?;
+            }
+            session = idle.done().await?;
+        } else {
+            tokio::time::sleep(Duration::from_secs(imap_config.poll_interval_secs.max(1))).await;
+        }
+    }
+}
+
+/// Connects to the IMAP server over TLS and logs in.
+async fn connect(imap_config: &ImapConfig) -> Result<ImapSession> {
+    let tcp_stream = TcpStream::connect((imap_config.host.as_str(), imap_config.port))
+        .await
+        .context("Failed to connect to the IMAP server")?;
+    let mut root_store = RootCertStore::empty();
+    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    let tls_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(root_store)
+        .with_no_client_auth();
+    let server_name =
+        ServerName::try_from(imap_config.host.clone()).context("Invalid IMAP server name")?;
+    let tls_stream = TlsConnector::from(Arc::new(tls_config))
+        .connect(server_name, tcp_stream)
+        .await
+        .context("Failed to establish TLS with the IMAP server")?;
+
+    let mut client = async_imap::Client::new(tls_stream);
+    client
+        .read_response()
+        .await?
+        .context("IMAP server closed the connection before its greeting")?;
+
+    let session = client
+        .login(&imap_config.username, &imap_config.password)
+        .await
+        .map_err(|(e, _)| e)
+        .context("Failed to log in to the IMAP server")?;
+    Ok(session)
+}
+
+/// Fetches a message and processes it as a reply if it was not processed before.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the message is done with and may be moved out of the mailbox.
+async fn process_message(
+    relayer_state: &Arc<RelayerState>,
+    imap_config: &ImapConfig,
+    session: &mut ImapSession,
+    uid_validity: u32,
+    uid: u32,
+) -> Result<bool> {
+    let fetches = session
+        .uid_fetch(uid.to_string(), "BODY.PEEK[]")
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    let Some(raw_email) = fetches.iter().find_map(|fetch| fetch.body()) else {
+        return Ok(false);
+    };
+
+    // Another replica may have claimed the message since the claimed messages were listed
+    if !claim_imap_message(&relayer_state.db, &imap_config.mailbox, uid_validity, uid).await? {
+        return Ok(true);
+    }
+
+    info!(LOG, "Processing email {} from {}", uid, imap_config.mailbox);
+    let raw_email = String::from_utf8_lossy(raw_email).into_owned();
+    match process_reply_email(relayer_state.clone(), raw_email).await {
+        Ok(_) => Ok(true),
+        // Rejected replies will not succeed on a retry, so they are only logged
+        Err((status, body)) if status.is_client_error() => {
+            info!(
+                LOG,
+                "Email {} from {} was rejected with {}: {}",
+                uid,
+                imap_config.mailbox,
+                status,
+                body.0
+            );
+            Ok(true)
+        }
+        // Internal errors are retried, so the message is released and left in the mailbox
+        Err((status, body)) => {
+            error!(
+                LOG,
+                "Failed to process email {} from {} with {}: {}",
+                uid,
+                imap_config.mailbox,
+                status,
+                body.0
+            );
+            release_imap_message(&relayer_state.db, &imap_config.mailbox, uid_validity, uid)
+                .await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Moves a message to another mailbox, falling back to copy and expunge without MOVE support.
+async fn move_message(
+    session: &mut ImapSession,
+    uid: u32,
+    mailbox: &str,
+    use_move: bool,
+) -> Result<()> {
+    let uid_set = uid.to_string();
+    if use_move {
+        session.uid_mv(&uid_set, mailbox).await?;
+        return Ok(());
+    }
+
+    session.uid_copy(&uid_set, mailbox).await?;
+    session
+        .uid_store(&uid_set, "+FLAGS (\\Deleted)")
+        .await?
+        .try_collect::<Vec<_>>()
+        .await?;
+    session.expunge().await?.try_collect::<Vec<_>>().await?;
+    Ok(())
+}
@@ -0,0 +1,297 @@
+//! Reception of replies over SMTP or LMTP.
+//!
+//! When enabled, the relayer accepts mail delivered to it directly, either over LMTP from a local
+//! mail server or over SMTP. The message is passed to the reply processing exactly as it was
+//! received, so the signed headers and body still verify against the sender's DKIM signature.
+
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Context, Result};
+use relayer_utils::LOG;
+use slog::{error, info};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{tcp::OwnedWriteHalf, TcpListener, TcpStream},
+};
+
+use crate::{
+    config::{LmtpConfig, MailProtocol},
+    handler::process_reply_email,
+    mail::is_same_email_address,
+    RelayerState,
+};
+
+/// The maximum length of a command line, including the trailing CRLF (RFC 5321 section 4.5.3.1.4).
+const MAX_COMMAND_BYTES: usize = 512;
+
+/// The time after which an unresponsive client is disconnected.
+const READ_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Accepts mail connections until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_lmtp_listener(relayer_state: RelayerState) {
+    let mut lmtp_config = relayer_state.config.lmtp.clone();
+    if lmtp_config.recipients.is_empty() {
+        lmtp_config.recipients = relayer_state.config.sending_address().into_iter().collect();
+    }
+    let lmtp_config = Arc::new(lmtp_config);
+    let relayer_state = Arc::new(relayer_state);
+
+    let listener = match TcpListener::bind(&lmtp_config.bind_address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(
+                LOG,
+                "Failed to bind mail listener to {}: {:?}", lmtp_config.bind_address, e
+            );
+            return;
+        }
+    };
+    info!(
+        LOG,
+        "Receiving replies over {:?} on {}", lmtp_config.protocol, lmtp_config.bind_address
+    );
+
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!(LOG, "Failed to accept mail connection: {:?}", e);
+                continue;
+            }
+        };
+
+        let relayer_state = relayer_state.clone();
+        let lmtp_config = lmtp_config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(&relayer_state, &lmtp_config, stream).await {
+                error!(LOG, "Mail connection from {} failed: {:?}", peer, e);
+            }
+        });
+    }
+}
+
+/// Runs a mail session with a client until it quits or disconnects.
+async fn handle_connection(
+    relayer_state: &Arc<RelayerState>,
+    lmtp_config: &LmtpConfig,
+    stream: TcpStream,
+) -> Result<()> {
+    let (read_half, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    let is_lmtp = lmtp_config.protocol == MailProtocol::Lmtp;
+    let hostname = &lmtp_config.hostname;
+
+    send_reply(
+        &mut writer,
+        &format!(
+            "220 {} {} ready",
+            hostname,
+            if is_lmtp { "LMTP" } else { "ESMTP" }
+        ),
+    )
+    .await?;
+
+    let mut has_sender = false;
+    let mut recipients: Vec<String> = Vec::new();
+    loop {
+        let Some(line) = read_line(&mut reader, MAX_COMMAND_BYTES).await? else {
+            return Ok(());
+        };
+        if !line.ends_with(b"\n") {
+            send_reply(&mut writer, "500 5.5.2 Line too long").await?;
+            return Ok(());
+        }
+
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        let (verb, argument) = line.split_once(' ').unwrap_or((line, ""));
+
+        let reply = match verb.to_ascii_uppercase().as_str() {
+            "LHLO" if is_lmtp => greeting(hostname, lmtp_config.max_message_bytes),
+            "EHLO" if !is_lmtp => greeting(hostname, lmtp_config.max_message_bytes),
+            "HELO" if !is_lmtp => format!("250 {}", hostname),
+            "MAIL" if has_sender => "503 5.5.1 Sender already specified".to_string(),
+            "MAIL" => match parse_path(argument, "FROM:") {
+                Some(_) => {
+                    has_sender = true;
+                    "250 2.1.0 OK".to_string()
+                }
+                None => "501 5.5.4 Syntax: MAIL FROM:<address>".to_string(),
+            },
+            "RCPT" if !has_sender => "503 5.5.1 Need MAIL command".to_string(),
+            "RCPT" => match parse_path(argument, "TO:") {
+                Some(address) if accepts(lmtp_config, &address) => {
+                    recipients.push(address);
+                    "250 2.1.5 OK".to_string()
+                }
+                Some(_) => "550 5.1.1 Mailbox unavailable".to_string(),
+                None => "501 5.5.4 Syntax: RCPT TO:<address>".to_string(),
+            },
+            "DATA" if recipients.is_empty() => "503 5.5.1 Need RCPT command".to_string(),
+            "DATA" => {
+                send_reply(&mut writer, "354 End data with <CR><LF>.<CR><LF>").await?;
+                let reply = match read_data(&mut reader, lmtp_config.max_message_bytes).await? {
+                    Some(raw_email) => deliver(relayer_state, raw_email).await,
+                    None => "552 5.3.4 Message too big".to_string(),
+                };
+
+                // LMTP reports the outcome for every recipient, SMTP once for the message
+                let replies = if is_lmtp { recipients.len() } else { 1 };
+                for _ in 0..replies {
+                    send_reply(&mut writer, &reply).await?;
+                }
+                has_sender = false;
+                recipients.clear();
+                continue;
+            }
+            "RSET" => {
+                has_sender = false;
+                recipients.clear();
+                "250 2.0.0 OK".to_string()
+            }
+            "NOOP" => "250 2.0.0 OK".to_string(),
+            "VRFY" => "252 2.5.2 Cannot verify user".to_string(),
+            "QUIT" => {
+                send_reply(&mut writer, "221 2.0.0 Bye").await?;
+                return Ok(());
+            }
+            _ => "502 5.5.2 Command not implemented".to_string(),
+        };
+        send_reply(&mut writer, &reply).await?;
+    }
+}
+
+/// Builds the reply to LHLO and EHLO listing the supported extensions.
+fn greeting(hostname: &str, max_message_bytes: usize) -> String {
+    format!(
+        "250-{}\r\n250-8BITMIME\r\n250-ENHANCEDSTATUSCODES\r\n250-PIPELINING\r\n250 SIZE {}",
+        hostname, max_message_bytes
+    )
+}
+
+/// Extracts the address from the argument of MAIL or RCPT, e.g. `TO:<relayer@example.com>`.
+fn parse_path(argument: &str, prefix: &str) -> Option<String> {
+    let argument = argument.trim_start();
+    if !argument
+        .get(..prefix.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(prefix))
+    {
+        return None;
+    }
+
+    let path = argument[prefix.len()..].trim_start();
+    let end = path.find('>')?;
+    Some(path.strip_prefix('<')?[..end - 1].to_string())
+}
+
+/// Checks whether mail for an address is accepted.
+///
+/// An empty list of recipients is only possible over LMTP, where the local mail server already
+/// routed the mail to the relayer.
+fn accepts(lmtp_config: &LmtpConfig, address: &str) -> bool {
+    lmtp_config.recipients.is_empty()
+        || lmtp_config
+            .recipients
+            .iter()
+            .any(|recipient| is_same_email_address(recipient, address))
+}
+
+/// Reads a line including its line ending, or at most `max_bytes` of it.
+///
+/// # Returns
+///
+/// A `Result` containing the line, or `None` if the client disconnected.
+async fn read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let read = tokio::time::timeout(
+        READ_TIMEOUT,
+        (&mut *reader)
+            .take(max_bytes as u64)
+            .read_until(b'\n', &mut line),
+    )
+    .await
+    .map_err(|_| anyhow!("Timed out waiting for the client"))??;
+
+    Ok((read > 0).then_some(line))
+}
+
+/// Reads the message sent after DATA up to the terminating dot, undoing the dot-stuffing.
+///
+/// # Returns
+///
+/// A `Result` containing the raw message, or `None` if it exceeds `max_bytes`.
+async fn read_data<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> Result<Option<Vec<u8>>> {
+    let mut data = Vec::new();
+    let mut too_large = false;
+    loop {
+        let line = read_line(reader, max_bytes)
+            .await?
+            .context("Client disconnected during DATA")?;
+        if line == b".\r\n" || line == b".\n" {
+            break;
+        }
+        if too_large {
+            continue;
+        }
+
+        let line = line.strip_prefix(b".").unwrap_or(&line[..]);
+        if data.len() + line.len() > max_bytes {
+            too_large = true;
+            data = Vec::new();
+            continue;
+        }
+        data.extend_from_slice(line);
+    }
+
+    Ok((!too_large).then_some(data))
+}
+
+/// Processes a received message as a reply and builds the reply to DATA.
+///
+/// Rejected replies are refused permanently, while internal errors are reported as temporary so
+/// the sending server delivers the message again later.
+async fn deliver(relayer_state: &Arc<RelayerState>, raw_email: Vec<u8>) -> String {
+    let Ok(raw_email) = String::from_utf8(raw_email) else {
+        return "554 5.6.0 Message is not valid UTF-8".to_string();
+    };
+
+    match process_reply_email(relayer_state.clone(), raw_email).await {
+        Ok(_) => "250 2.0.0 Message accepted".to_string(),
+        Err((status, body)) if status.is_client_error() => {
+            info!(
+                LOG,
+                "Received email was rejected with {}: {}", status, body.0
+            );
+            let reason = body.0["error"]
+                .as_str()
+                .unwrap_or("Message rejected")
+                .replace(['\r', '\n'], " ");
+            format!("550 5.7.1 {}", reason)
+        }
+        Err((status, body)) => {
+            error!(
+                LOG,
+                "Failed to process received email with {}: {}", status, body.0
+            );
+            "451 4.3.0 Temporary failure, please try again later".to_string()
+        }
+    }
+}
+
+/// Sends a reply line to the client.
+async fn send_reply(writer: &mut OwnedWriteHalf, reply: &str) -> Result<()> {
+    writer
+        .write_all(format!("{}\r\n", reply).as_bytes())
+        .await?;
+    Ok(())
+}
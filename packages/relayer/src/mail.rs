@@ -1,8 +1,11 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
-use ethers::types::U256;
+use anyhow::{anyhow, bail, Context, Result};
+use ethers::abi::AbiDecode;
+use ethers::types::{U256, U64};
+use ethers::utils::hex;
 use handlebars::Handlebars;
+use regex::Regex;
 use relayer_utils::{ParsedEmail, LOG};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -13,10 +16,16 @@ use uuid::Uuid;
 use crate::{
     abis::EmailAuthMsg,
     chain::ChainClient,
-    command::get_encoded_command_params,
+    command::{get_encoded_command_params, get_skipped_command_prefix},
     dkim::check_and_update_dkim,
-    model::{insert_expected_reply, update_request, RequestModel, RequestStatus},
-    prove::generate_email_proof,
+    model::{
+        finish_request, get_encoded_email_auth_msg, get_on_chain_transaction,
+        insert_expected_reply, insert_on_chain_transaction, update_on_chain_transaction,
+        update_request, ErrorCode, RequestModel, RequestStatus,
+    },
+    prove::{generate_email_proof, is_legacy_request, RecipientCommitment},
+    schema::OnChainTxSchema,
+    webhook::notify_status,
     RelayerState,
 };
 
@@ -50,6 +59,19 @@ pub enum EmailEvent {
         account_code: Option<String>,
         subject: String,
         body: String,
+        /// Whether the command goes in the subject for the legacy circuit.
+        legacy: bool,
+    },
+    Reminder {
+        request_id: Uuid,
+        email_address: String,
+        command: String,
+        account_code: Option<String>,
+        subject: String,
+        body: String,
+        /// Whether the command goes in the subject for the legacy circuit.
+        legacy: bool,
+        expires_at: String,
     },
     Ack {
         email_addr: String,
@@ -89,13 +111,9 @@ pub async fn handle_email_event(event: EmailEvent, relayer_state: RelayerState)
             account_code,
             subject,
             body,
+            legacy,
         } => {
-            // Prepare the command with the account code if it exists
-            let command = if let Some(code) = account_code {
-                format!("{} Code {}", command, code)
-            } else {
-                command
-            };
+            let (command, subject) = format_command(command, account_code, subject, legacy);
 
             // Create the plain text body
             let body_plain = format!(
@@ -104,14 +122,8 @@ pub async fn handle_email_event(event: EmailEvent, relayer_state: RelayerState)
                 request_id
             );
 
-            // Prepare data for HTML rendering
-            let render_data = serde_json::json!({
-                "body": body,
-                "requestId": request_id,
-                "command": command,
-            });
             let body_html =
-                render_html("command_template.html", render_data, relayer_state.clone()).await?;
+                render_command_html(request_id, &command, &body, legacy, &relayer_state).await?;
 
             // Create and send the email
             let email = EmailMessage {
@@ -131,9 +143,51 @@ pub async fn handle_email_event(event: EmailEvent, relayer_state: RelayerState)
                 Some(ExpectsReply::new(request_id)),
                 relayer_state.clone(),
             )
-            .await?;
+            .await
+            .context(ErrorCode::EmailSendFailed)?;
 
-            update_request(&relayer_state.db, request_id, RequestStatus::EmailSent).await?;
+            // A request that ended while the email was sent keeps its status
+            if update_request(&relayer_state.db, request_id, RequestStatus::EmailSent).await? {
+                notify_status(&relayer_state, request_id, RequestStatus::EmailSent).await;
+            }
+        }
+        EmailEvent::Reminder {
+            request_id,
+            email_address,
+            command,
+            account_code,
+            subject,
+            body,
+            legacy,
+            expires_at,
+        } => {
+            let (command, subject) = format_command(command, account_code, subject, legacy);
+
+            let body_plain = format!(
+                "Reminder: your ZK Email request {} expires on {} UTC. \
+                Reply to this email to approve it.",
+                request_id, expires_at
+            );
+
+            // Repeat the command email, so replying to the reminder works like replying to it
+            let body = format!(
+                "Reminder: this request expires on {} UTC. {}",
+                expires_at, body
+            );
+            let body_html =
+                render_command_html(request_id, &command, &body, legacy, &relayer_state).await?;
+
+            let email = EmailMessage {
+                to: email_address,
+                subject,
+                reference: None,
+                reply_to: None,
+                body_plain,
+                body_html,
+                body_attachments: None,
+            };
+
+            send_email(email, Some(ExpectsReply::new(request_id)), relayer_state).await?;
         }
         EmailEvent::Completion {
             email_addr,
@@ -240,6 +294,65 @@ pub async fn handle_email_event(event: EmailEvent, relayer_state: RelayerState)
     Ok(())
 }
 
+/// Builds the command of a command email and the subject it is sent with.
+///
+/// # Arguments
+///
+/// * `command` - The command the user approves by replying.
+/// * `account_code` - The account code appended to the command, if any.
+/// * `subject` - The subject requested for the email.
+/// * `legacy` - Whether the command goes in the subject for the legacy circuit.
+///
+/// # Returns
+///
+/// The command and the subject of the email.
+fn format_command(
+    command: String,
+    account_code: Option<String>,
+    subject: String,
+    legacy: bool,
+) -> (String, String) {
+    // Prepare the command with the account code if it exists
+    let command = if let Some(code) = account_code {
+        format!("{} Code {}", command, code)
+    } else {
+        command
+    };
+
+    // The legacy circuit reads the command from the subject, which the reply keeps
+    let subject = if legacy { command.clone() } else { subject };
+    (command, subject)
+}
+
+/// Renders the HTML body of a command email.
+///
+/// # Arguments
+///
+/// * `request_id` - The unique identifier of the request.
+/// * `command` - The command the user approves by replying.
+/// * `body` - The text shown above the command.
+/// * `legacy` - Whether the command goes in the subject for the legacy circuit.
+/// * `relayer_state` - The current state of the relayer.
+///
+/// # Returns
+///
+/// A `Result` containing the rendered HTML string.
+async fn render_command_html(
+    request_id: Uuid,
+    command: &str,
+    body: &str,
+    legacy: bool,
+    relayer_state: &RelayerState,
+) -> Result<String> {
+    let render_data = serde_json::json!({
+        "body": body,
+        "requestId": request_id,
+        "command": command,
+        "legacy": legacy,
+    });
+    render_html("command_template.html", render_data, relayer_state.clone()).await
+}
+
 /// Renders an HTML template with the given data.
 ///
 /// # Arguments
@@ -271,7 +384,7 @@ async fn render_html(
     Ok(template)
 }
 
-/// Sends an email using the configured SMTP server.
+/// Sends an email using the configured email transport.
 ///
 /// # Arguments
 ///
@@ -286,38 +399,125 @@ async fn send_email(
     expects_reply: Option<ExpectsReply>,
     relayer_state: RelayerState,
 ) -> Result<()> {
-    // Send POST request to email server
-    let response = relayer_state
-        .http_client
-        .post(format!("{}/api/sendEmail", relayer_state.config.smtp_url))
-        .json(&email)
-        .send()
-        .await?;
-
-    // Check if the email was sent successfully
-    if !response.status().is_success() {
-        return Err(anyhow::anyhow!(
-            "Failed to send email: {}",
-            response.text().await.unwrap_or_default()
-        ));
-    }
+    let message_id = relayer_state.email_sender.send(&email).await?;
 
     // Handle expected reply if necessary
     if let Some(expects_reply) = expects_reply {
-        let response_body: EmailResponse = response.json().await?;
-
-        let message_id = response_body.message_id;
         insert_expected_reply(&relayer_state.db, &message_id, expects_reply.request_id).await?;
     }
 
     Ok(())
 }
 
-/// Represents the response from the email server after sending an email.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct EmailResponse {
-    status: String,
-    message_id: String,
+/// Extracts the message IDs an email replies to from its `In-Reply-To` and `References` headers.
+///
+/// Each ID is returned both with and without angle brackets, so it matches however the ID was
+/// stored when the original email was sent.
+///
+/// # Arguments
+///
+/// * `raw_email` - The raw email content.
+///
+/// # Returns
+///
+/// The message IDs, starting with the ones from `In-Reply-To`.
+pub fn get_reply_message_ids(raw_email: &str) -> Vec<String> {
+    let message_id_regex = Regex::new(r"<([^<>\s]+)>").unwrap();
+
+    // The header section ends at the first empty line
+    let header_section = raw_email
+        .split("\r\n\r\n")
+        .next()
+        .unwrap_or_default()
+        .split("\n\n")
+        .next()
+        .unwrap_or_default();
+
+    // Unfold header lines that continue on the next line
+    let mut headers: Vec<String> = vec![];
+    for line in header_section.lines() {
+        match headers.last_mut() {
+            Some(header) if line.starts_with([' ', '\t']) => header.push_str(line),
+            _ => headers.push(line.to_string()),
+        }
+    }
+
+    let mut message_ids = vec![];
+    for name in ["in-reply-to", "references"] {
+        for header in &headers {
+            let Some((key, value)) = header.split_once(':') else {
+                continue;
+            };
+            if !key.trim().eq_ignore_ascii_case(name) {
+                continue;
+            }
+            for caps in message_id_regex.captures_iter(value) {
+                message_ids.push(caps[0].to_string());
+                message_ids.push(caps[1].to_string());
+            }
+        }
+    }
+    message_ids
+}
+
+/// The domains whose mailboxes ignore a `+tag` suffix of the local part.
+const SUBADDRESSING_DOMAINS: [&str; 11] = [
+    "gmail.com",
+    "googlemail.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "icloud.com",
+    "me.com",
+    "fastmail.com",
+    "proton.me",
+    "protonmail.com",
+    "pm.me",
+];
+
+/// Normalizes an email address so that aliases of the same mailbox compare equal.
+///
+/// The address is trimmed and lowercased. For providers that deliver `+tag` aliases to the same
+/// mailbox, the suffix is removed. For Gmail, dots in the local part are ignored and
+/// `googlemail.com` is treated as `gmail.com`. Other providers may treat `+` as part of the
+/// address, so their local parts are kept.
+///
+/// # Arguments
+///
+/// * `email_addr` - The email address to normalize.
+///
+/// # Returns
+///
+/// The normalized email address.
+pub fn normalize_email_address(email_addr: &str) -> String {
+    let email_addr = email_addr.trim().to_lowercase();
+    let Some((local_part, domain)) = email_addr.rsplit_once('@') else {
+        return email_addr;
+    };
+
+    let local_part = if SUBADDRESSING_DOMAINS.contains(&domain) {
+        local_part.split('+').next().unwrap_or(local_part)
+    } else {
+        local_part
+    };
+    match domain {
+        "gmail.com" | "googlemail.com" => format!("{}@gmail.com", local_part.replace('.', "")),
+        _ => format!("{}@{}", local_part, domain),
+    }
+}
+
+/// Checks whether two email addresses refer to the same mailbox.
+///
+/// # Arguments
+///
+/// * `a` - The first email address.
+/// * `b` - The second email address.
+///
+/// # Returns
+///
+/// `true` if both addresses normalize to the same address.
+pub fn is_same_email_address(a: &str, b: &str) -> bool {
+    normalize_email_address(a) == normalize_email_address(b)
 }
 
 /// Represents an expectation of a reply to an email.
@@ -338,65 +538,283 @@ impl ExpectsReply {
     }
 }
 
-/// Processes an incoming email and interacts with the blockchain to complete the request.
+/// Processes an incoming email and generates the email authentication message for the request.
+///
+/// This asynchronous function parses the email, verifies DKIM, generates the email authentication
+/// message and saves it to the database. Submitting the message on-chain and completing the request
+/// are left to the caller. Errors carry the `ErrorCode` of the failed step as context.
 ///
-/// This asynchronous function parses the email, verifies DKIM, generates an email authentication message,
-/// and interacts with the blockchain to execute the transaction. It updates the request status and returns
-/// an `EmailEvent` indicating the completion of the process.
+/// A message already generated for the request or for the same email is returned as stored, so a
+/// retried or redelivered reply is not proven twice.
 ///
 /// # Arguments
 ///
-/// * `email` - The raw email content as a `String`.
+/// * `email` - The raw email content as a `&str`.
 /// * `request` - The `RequestModel` containing details of the request associated with the email.
 /// * `relayer_state` - The current state of the relayer, containing configuration and state information.
 ///
 /// # Returns
 ///
 /// A `Result` containing:
-/// - `Ok`: An `EmailEvent::Completion` with details of the completed transaction.
+/// - `Ok`: The generated `EmailAuthMsg`.
 /// - `Err`: An error if any step in the process fails.
 pub async fn handle_email(
-    email: String,
+    email: &str,
     request: RequestModel,
     relayer_state: RelayerState,
-) -> Result<EmailEvent> {
+) -> Result<EmailAuthMsg> {
     // Parse the email from the raw content
-    let parsed_email = ParsedEmail::new_from_raw_email(&email).await?;
+    let parsed_email = ParsedEmail::new_from_raw_email(email)
+        .await
+        .context(ErrorCode::InvalidEmail)?;
 
     info!(LOG, "Parsed email: {:?}", parsed_email);
 
+    if let Some(email_auth_msg) = get_saved_email_auth_msg(&relayer_state, request.id, None).await?
+    {
+        info!(
+            LOG,
+            "Email auth msg already generated for request {}", request.id
+        );
+        return Ok(email_auth_msg);
+    }
+
     let chain_client = ChainClient::setup(
         request.clone().email_tx_auth.chain,
         relayer_state.clone().config.chains,
     )
-    .await?;
+    .await
+    .context(ErrorCode::Internal)?;
 
     // Check and update DKIM using the parsed email and chain client
     check_and_update_dkim(
         &parsed_email,
         request.email_tx_auth.dkim_contract_address,
-        chain_client.clone(),
+        request.email_tx_auth.dkim_registry_type,
+        &request.email_tx_auth.chain,
+        chain_client,
         relayer_state.clone(),
     )
-    .await?;
+    .await
+    .context(ErrorCode::DkimUpdateFailed)?;
 
     // Generate the email authentication message
-    let email_auth_msg = get_email_auth_msg(&email, request.clone(), relayer_state.clone()).await?;
+    let (email_auth_msg, recipient) =
+        get_email_auth_msg(email, request.clone(), relayer_state.clone()).await?;
     info!(LOG, "Email auth msg: {:?}", email_auth_msg);
-    email_auth_msg.save(&relayer_state.db, request.id).await?;
+    let saved = email_auth_msg
+        .save(
+            &relayer_state.db,
+            request.id,
+            parsed_email.get_message_id().ok(),
+            recipient.as_ref(),
+        )
+        .await
+        .context(ErrorCode::Internal)?;
+    if !saved {
+        // The same email was proven concurrently, the message stored first is the result
+        let email_nullifier = format!("0x{}", hex::encode(email_auth_msg.proof.email_nullifier));
+        if let Some(stored) =
+            get_saved_email_auth_msg(&relayer_state, request.id, Some(&email_nullifier)).await?
+        {
+            info!(
+                LOG,
+                "Email auth msg was already saved for request {}", request.id
+            );
+            return Ok(stored);
+        }
+    }
 
     info!(LOG, "Email auth msg saved");
 
+    Ok(email_auth_msg)
+}
+
+/// Retrieves the email auth message stored for a request or an email nullifier.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `request_id` - The unique identifier of the request.
+/// * `email_nullifier` - The hex-encoded email nullifier of a generated message, if known.
+///
+/// # Returns
+///
+/// A `Result` containing the stored `EmailAuthMsg`, if any.
+async fn get_saved_email_auth_msg(
+    relayer_state: &RelayerState,
+    request_id: Uuid,
+    email_nullifier: Option<&str>,
+) -> Result<Option<EmailAuthMsg>> {
+    let encoded_msg = get_encoded_email_auth_msg(&relayer_state.db, request_id, email_nullifier)
+        .await
+        .context(ErrorCode::Internal)?;
+    encoded_msg
+        .map(|encoded_msg| EmailAuthMsg::decode(encoded_msg).context(ErrorCode::Internal))
+        .transpose()
+}
+
+/// Marks the request as finished and builds the completion email for the reply.
+///
+/// # Arguments
+///
+/// * `email` - The raw content of the reply email.
+/// * `request_id` - The unique identifier of the request.
+/// * `relayer_state` - The current state of the relayer.
+///
+/// # Returns
+///
+/// A `Result` containing an `EmailEvent::Completion` addressed to the sender of the reply, or
+/// `None` if the request was already finished and the completion email was sent before.
+pub async fn complete_request(
+    email: &str,
+    request_id: Uuid,
+    relayer_state: &RelayerState,
+) -> Result<Option<EmailEvent>> {
+    let parsed_email = ParsedEmail::new_from_raw_email(email)
+        .await
+        .context(ErrorCode::InvalidEmail)?;
+
     // Update the request status to finished in the database
-    update_request(&relayer_state.db, request.id, RequestStatus::Finished).await?;
+    if !finish_request(&relayer_state.db, request_id).await? {
+        return Ok(None);
+    }
+    notify_status(relayer_state, request_id, RequestStatus::Finished).await;
 
     // Return a completion event with transaction details
-    Ok(EmailEvent::Completion {
+    Ok(Some(EmailEvent::Completion {
         email_addr: parsed_email.get_from_addr()?,
-        request_id: request.id,
+        request_id,
         original_subject: parsed_email.get_subject_all()?,
         original_message_id: parsed_email.get_message_id().ok(),
-    })
+    }))
+}
+
+/// Submits the email authentication message on-chain and records the transaction.
+///
+/// The request is moved to `PerformingOnChainTransaction` while the transaction is pending.
+/// The transaction hash is recorded as soon as it is sent, so a retried job waits for the
+/// recorded transaction instead of sending it again. Failed attempts are recorded as well, so
+/// they show up in the request status.
+///
+/// # Arguments
+///
+/// * `request` - The request the email authentication message was generated for.
+/// * `on_chain` - The target contract and calldata shape of the transaction.
+/// * `email_auth_msg` - The email authentication message to submit.
+/// * `relayer_state` - The current state of the relayer.
+///
+/// # Returns
+///
+/// A `Result` indicating whether the transaction was confirmed.
+pub async fn submit_email_auth_msg(
+    request: &RequestModel,
+    on_chain: &OnChainTxSchema,
+    email_auth_msg: EmailAuthMsg,
+    relayer_state: &RelayerState,
+) -> Result<()> {
+    let recorded = get_on_chain_transaction(&relayer_state.db, request.id).await?;
+    if let Some(recorded) = &recorded {
+        if recorded.receipt.is_some() && recorded.error.is_none() {
+            info!(
+                LOG,
+                "Email auth msg already submitted for request {}", request.id
+            );
+            return Ok(());
+        }
+    }
+
+    if !update_request(
+        &relayer_state.db,
+        request.id,
+        RequestStatus::PerformingOnChainTransaction,
+    )
+    .await?
+    {
+        bail!("Request {} has already ended", request.id);
+    }
+    notify_status(
+        relayer_state,
+        request.id,
+        RequestStatus::PerformingOnChainTransaction,
+    )
+    .await;
+
+    let chain_client = ChainClient::setup(
+        request.email_tx_auth.chain.clone(),
+        relayer_state.config.chains.clone(),
+    )
+    .await
+    .context(ErrorCode::Internal)?;
+
+    // A transaction sent by an earlier attempt is awaited instead of being sent again
+    let pending_tx_hash = recorded
+        .filter(|recorded| recorded.error.is_none())
+        .and_then(|recorded| recorded.tx_hash);
+    let tx_hash = match pending_tx_hash {
+        Some(tx_hash) => {
+            info!(LOG, "Resuming on-chain transaction {}", tx_hash);
+            tx_hash
+        }
+        None => match chain_client
+            .send_email_auth_msg(on_chain, email_auth_msg)
+            .await
+        {
+            Ok(tx_hash) => {
+                let tx_hash = format!("0x{}", hex::encode(tx_hash.as_bytes()));
+                insert_on_chain_transaction(
+                    &relayer_state.db,
+                    request.id,
+                    Some(tx_hash.clone()),
+                    None,
+                    None,
+                )
+                .await?;
+                tx_hash
+            }
+            Err(e) => {
+                insert_on_chain_transaction(
+                    &relayer_state.db,
+                    request.id,
+                    None,
+                    None,
+                    Some(e.to_string()),
+                )
+                .await?;
+                return Err(e.context(ErrorCode::TransactionFailed));
+            }
+        },
+    };
+
+    // An error while waiting leaves the transaction pending, so the next attempt waits again
+    let receipt = chain_client
+        .wait_for_transaction(tx_hash.parse().context(ErrorCode::Internal)?)
+        .await
+        .context(ErrorCode::TransactionFailed)?;
+    let error = match &receipt {
+        None => Some(format!("Transaction {} was dropped", tx_hash)),
+        // A receipt with status 0 means the transaction was mined but reverted
+        Some(receipt) if receipt.status != Some(U64::from(1)) => {
+            Some(format!("Transaction {} reverted", tx_hash))
+        }
+        Some(_) => None,
+    };
+    update_on_chain_transaction(
+        &relayer_state.db,
+        request.id,
+        &tx_hash,
+        receipt.as_ref().map(serde_json::to_value).transpose()?,
+        error.clone(),
+    )
+    .await?;
+
+    match error {
+        Some(error) => Err(anyhow!(error).context(ErrorCode::TransactionFailed)),
+        None => {
+            info!(LOG, "Email auth msg submitted on-chain: {}", tx_hash);
+            Ok(())
+        }
+    }
 }
 
 /// Generates the email authentication message.
@@ -407,21 +825,86 @@ pub async fn handle_email(
 ///
 /// # Returns
 ///
-/// A `Result` containing the `EmailAuthMsg`, `EmailProof`, and account salt, or an `EmailError`.
+/// A `Result` containing the `EmailAuthMsg` and the `RecipientCommitment` of recipient-bound
+/// proofs, or an `EmailError`.
 async fn get_email_auth_msg(
     email: &str,
     request: RequestModel,
     relayer_state: RelayerState,
-) -> Result<EmailAuthMsg> {
-    let command_params_encoded = get_encoded_command_params(email, request.clone()).await?;
+) -> Result<(EmailAuthMsg, Option<RecipientCommitment>)> {
+    let legacy = is_legacy_request(&relayer_state.config, &request.email_tx_auth);
+    let command_params_encoded = get_encoded_command_params(email, request.clone(), legacy)
+        .await
+        .context(ErrorCode::InvalidCommand)?;
     info!(LOG, "Generating email proof");
-    let email_proof = generate_email_proof(email, request.clone(), relayer_state).await?;
+    let (email_proof, recipient) = generate_email_proof(email, request.clone(), relayer_state)
+        .await
+        .context(ErrorCode::ProvingFailed)?;
     info!(LOG, "Email proof generated");
+    // The command of a legacy reply is its subject, which follows the `Re: ` of the reply
+    let skipped_command_prefix = if legacy {
+        get_skipped_command_prefix(&email_proof.masked_command)
+    } else {
+        0
+    };
     let email_auth_msg = EmailAuthMsg {
         template_id: request.email_tx_auth.template_id,
         command_params: command_params_encoded,
-        skipped_command_prefix: U256::zero(),
+        skipped_command_prefix: U256::from(skipped_command_prefix),
         proof: email_proof,
     };
-    Ok(email_auth_msg)
+    Ok((email_auth_msg, recipient))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_email_address_ignores_case_and_whitespace() {
+        assert_eq!(
+            normalize_email_address("  Alice@Example.COM "),
+            "alice@example.com"
+        );
+    }
+
+    #[test]
+    fn normalize_email_address_strips_tags_of_subaddressing_providers() {
+        assert_eq!(
+            normalize_email_address("alice+wallet@outlook.com"),
+            "alice@outlook.com"
+        );
+        assert_eq!(
+            normalize_email_address("Alice+Wallet@iCloud.com"),
+            "alice@icloud.com"
+        );
+    }
+
+    #[test]
+    fn normalize_email_address_normalizes_gmail_aliases() {
+        assert_eq!(
+            normalize_email_address("A.Lice+wallet@googlemail.com"),
+            "alice@gmail.com"
+        );
+        assert!(is_same_email_address(
+            "alice@gmail.com",
+            "a.l.i.c.e+relayer@GMAIL.com"
+        ));
+    }
+
+    #[test]
+    fn normalize_email_address_keeps_tags_of_other_providers() {
+        assert_eq!(
+            normalize_email_address("alice+wallet@example.com"),
+            "alice+wallet@example.com"
+        );
+        assert!(!is_same_email_address(
+            "alice@example.com",
+            "alice+wallet@example.com"
+        ));
+        assert!(!is_same_email_address(
+            "a.lice@example.com",
+            "alice@example.com"
+        ));
+    }
 }
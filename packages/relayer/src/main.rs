@@ -1,16 +1,29 @@
 mod abis;
+mod auth;
 mod chain;
 mod command;
 mod config;
 mod constants;
 mod dkim;
+mod dkim_revocation;
+mod dkim_sync;
+mod events;
+mod expiry;
 mod handler;
+mod imap;
+mod lmtp;
 mod mail;
 mod model;
 mod prove;
+mod prover;
+mod prover_pool;
+mod queue;
+mod rate_limit;
 mod route;
 mod schema;
+mod sender;
 mod statics;
+mod webhook;
 
 use std::sync::Arc;
 
@@ -19,30 +32,40 @@ use axum::http::{
     header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
     Method,
 };
+use prover_pool::ProverPool;
 use relayer_utils::LOG;
 use reqwest::Client;
 use route::create_router;
+use sender::{create_email_sender, EmailSender};
 use slog::info;
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use tokio::sync::broadcast;
 use tower_http::cors::CorsLayer;
 
-use config::Config;
+use config::{Config, ProverConfig};
+use events::{RequestStatusEvent, STATUS_EVENTS_CAPACITY};
 
 /// Represents the state of the relayer, including HTTP client, configuration, and database pool.
 #[derive(Debug, Clone)]
 pub struct RelayerState {
     /// The HTTP client used for making network requests.
     http_client: Client,
+    /// The transport used to send emails.
+    email_sender: Arc<dyn EmailSender>,
+    /// The remote prover services proofs are requested from.
+    prover_pool: Arc<ProverPool>,
     /// The configuration settings for the relayer.
     config: Config,
     /// The connection pool for the PostgreSQL database.
     db: Pool<Postgres>,
+    /// The broadcast of request status changes, fed by the status listener.
+    status_events: broadcast::Sender<RequestStatusEvent>,
 }
 
 /// The main entry point for the relayer application.
 ///
 /// This asynchronous function loads the configuration, establishes a database connection,
-/// sets up CORS, creates the router, and starts the server.
+/// spawns the background tasks, sets up CORS, creates the router, and starts the server.
 ///
 /// # Returns
 ///
@@ -66,13 +89,68 @@ async fn main() -> Result<()> {
         .allow_methods([Method::GET, Method::POST])
         .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
 
-    // Create the router with the relayer state and apply the CORS layer
-    let relayer = create_router(Arc::new(RelayerState {
-        http_client: Client::new(),
+    // Set up the transport used to send emails
+    let http_client = Client::new();
+    let email_sender = create_email_sender(&config, http_client.clone())?;
+
+    // Set up the prover services proofs are requested from
+    let prover_pool = Arc::new(ProverPool::new(&config, http_client.clone()));
+
+    let relayer_state = RelayerState {
+        http_client,
+        email_sender,
+        prover_pool,
         config: config.clone(),
         db: pool.clone(),
-    }))
-    .layer(cors);
+        status_events: broadcast::channel(STATUS_EVENTS_CAPACITY).0,
+    };
+
+    // Spawn the workers that process queued proving and on-chain jobs
+    for worker_id in 0..config.queue.workers {
+        tokio::spawn(queue::run_worker(worker_id, relayer_state.clone()));
+    }
+    info!(LOG, "Started {} job workers.", config.queue.workers);
+
+    // Spawn the listener that broadcasts status changes to the status streams
+    tokio::spawn(events::run_status_listener(relayer_state.clone()));
+
+    // Spawn the pruning of the counters of ended rate limit windows
+    if config.rate_limit.enabled {
+        tokio::spawn(rate_limit::run_rate_limit_pruning(relayer_state.clone()));
+    }
+
+    // Spawn the scheduler that reminds and expires requests waiting for a reply
+    tokio::spawn(expiry::run_expiry_scheduler(relayer_state.clone()));
+
+    // Read replies from the IMAP mailbox if the relayer does not receive them over HTTP
+    if config.imap.enabled {
+        tokio::spawn(imap::run_imap_ingestion(relayer_state.clone()));
+    }
+
+    // Accept replies delivered to the relayer over SMTP or LMTP
+    if config.lmtp.enabled {
+        tokio::spawn(lmtp::run_lmtp_listener(relayer_state.clone()));
+    }
+
+    // Spawn the sync that registers the DKIM keys of popular domains ahead of replies
+    if config.dkim.sync_enabled {
+        tokio::spawn(dkim_sync::run_dkim_sync(relayer_state.clone()));
+    }
+
+    // Spawn the monitor that flags DKIM keys their domains no longer publish
+    if config.dkim.revocation_check_enabled {
+        tokio::spawn(dkim_revocation::run_revocation_monitor(
+            relayer_state.clone(),
+        ));
+    }
+
+    // Spawn the health probes of the prover services
+    if matches!(config.prover, ProverConfig::Remote) && config.prover_pool.health_check_enabled {
+        tokio::spawn(prover_pool::run_prover_health_checks(relayer_state.clone()));
+    }
+
+    // Create the router with the relayer state and apply the CORS layer
+    let relayer = create_router(Arc::new(relayer_state)).layer(cors);
 
     // Bind the server to the specified port and start listening for requests
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", config.port)).await?;
@@ -5,6 +5,7 @@ use sqlx::types::Json;
 use sqlx::{FromRow, PgPool};
 use uuid::Uuid;
 
+use crate::queue::Job;
 use crate::schema::EmailTxAuthSchema;
 
 /// Represents a request model with details about the request status and associated email transaction authentication.
@@ -14,12 +15,157 @@ pub struct RequestModel {
     /// The unique identifier for the request.
     pub id: Uuid,
     /// The current status of the request.
-    pub status: String,
+    pub status: RequestStatus,
     /// The timestamp when the request was last updated.
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<NaiveDateTime>,
     #[serde(rename = "body")]
     pub email_tx_auth: EmailTxAuthSchema,
+    /// The machine-readable code of the error that ended the request.
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<String>,
+    /// The message of the error that ended the request.
+    #[serde(rename = "errorMessage")]
+    pub error_message: Option<String>,
+    /// The timestamp after which replies to the request are rejected.
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<NaiveDateTime>,
+    /// The group the request was submitted in, if it was part of a batch.
+    #[serde(rename = "groupId")]
+    pub group_id: Option<Uuid>,
+    /// The API client that submitted the request, if auth was enabled.
+    #[serde(rename = "clientId")]
+    pub client_id: Option<Uuid>,
+}
+
+/// Represents an on-chain transaction submitted for a request.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct OnChainTransactionModel {
+    /// The hash of the transaction, if it was sent.
+    #[serde(rename = "txHash")]
+    pub tx_hash: Option<String>,
+    /// The receipt of the confirmed transaction.
+    pub receipt: Option<serde_json::Value>,
+    /// The error that occurred while sending the transaction.
+    pub error: Option<String>,
+    /// The timestamp when the transaction was recorded.
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Represents a group of requests submitted together.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct RequestGroupModel {
+    /// The unique identifier for the group.
+    pub id: Uuid,
+    /// The number of proven replies after which the group is complete.
+    pub threshold: i32,
+    /// The timestamp when the group was created.
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<NaiveDateTime>,
+    /// The API client that submitted the group, if auth was enabled.
+    #[serde(rename = "clientId")]
+    pub client_id: Option<Uuid>,
+}
+
+/// Represents the progress of a request within its group.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct GroupRequestModel {
+    /// The unique identifier for the request.
+    pub id: Uuid,
+    /// The current status of the request.
+    pub status: RequestStatus,
+    /// The email address the command email was sent to.
+    #[serde(rename = "emailAddress")]
+    pub email_address: Option<String>,
+    /// Indicates whether the reply to the request has been proven.
+    pub proven: bool,
+    /// The machine-readable code of the error that ended the request.
+    #[serde(rename = "errorCode")]
+    pub error_code: Option<String>,
+}
+
+/// Identifies a DKIM public key hash in the registry of a chain.
+#[derive(Debug, Clone, FromRow, Deserialize, Serialize)]
+pub struct DkimKeyModel {
+    /// The name of the chain the registry is on.
+    pub chain: String,
+    /// The address of the DKIM registry.
+    pub registry_address: String,
+    /// The domain of the key.
+    pub domain: String,
+    /// The DKIM selector of the key.
+    pub selector: String,
+    /// The hash of the public key, as a hex string.
+    pub public_key_hash: String,
+}
+
+/// Represents a registered DKIM key that its domain no longer publishes as registered.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct RevokedDkimKeyModel {
+    /// The name of the chain the registry is on.
+    pub chain: String,
+    /// The address of the DKIM registry.
+    #[serde(rename = "registryAddress")]
+    pub registry_address: String,
+    /// The domain of the key.
+    pub domain: String,
+    /// The DKIM selector of the key.
+    pub selector: String,
+    /// The hash of the public key, as a hex string.
+    #[serde(rename = "publicKeyHash")]
+    pub public_key_hash: String,
+    /// Why the key was flagged.
+    pub reason: Option<String>,
+    /// The timestamp when the key was first flagged.
+    #[serde(rename = "flaggedAt")]
+    pub flagged_at: Option<NaiveDateTime>,
+    /// The timestamp when the key was confirmed revoked by an empty key in DNS.
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<NaiveDateTime>,
+    /// The hash of the transaction revoking the key in the registry, if it was submitted.
+    #[serde(rename = "revocationTxHash")]
+    pub revocation_tx_hash: Option<String>,
+}
+
+/// Represents an attempt to deliver a webhook callback for a request.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct WebhookDeliveryModel {
+    /// The URL the callback was posted to.
+    pub url: String,
+    /// The request status the callback reported.
+    pub status: RequestStatus,
+    /// The attempt number of the delivery.
+    pub attempt: i32,
+    /// The HTTP status returned by the callback URL, if it responded.
+    #[serde(rename = "responseStatus")]
+    pub response_status: Option<i32>,
+    /// The error of a failed delivery.
+    pub error: Option<String>,
+    /// The timestamp of the attempt.
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<NaiveDateTime>,
+}
+
+/// Represents a client authorized to use the relayer API.
+#[derive(Debug, FromRow, Deserialize, Serialize, Clone)]
+#[allow(non_snake_case)]
+pub struct ApiClientModel {
+    /// The unique identifier for the client.
+    pub id: Uuid,
+    /// The name of the client.
+    pub name: String,
+    /// The timestamp when the client was created.
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<NaiveDateTime>,
+    /// The timestamp when the client's API key was revoked.
+    #[serde(rename = "revokedAt")]
+    pub revoked_at: Option<NaiveDateTime>,
 }
 
 /// Represents an expected reply model with details about the message and request.
@@ -37,7 +183,7 @@ pub struct ExpectedReplyModel {
     pub created_at: chrono::DateTime<chrono::Utc>,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "status_enum")]
 pub enum RequestStatus {
     #[sqlx(rename = "Request received")]
@@ -52,6 +198,174 @@ pub enum RequestStatus {
     PerformingOnChainTransaction,
     #[sqlx(rename = "Finished")]
     Finished,
+    #[sqlx(rename = "Transaction failed")]
+    TransactionFailed,
+    #[sqlx(rename = "Failed")]
+    Failed,
+    #[sqlx(rename = "Expired")]
+    Expired,
+    #[sqlx(rename = "Rejected")]
+    Rejected,
+}
+
+/// Represents the cause of a failed, expired or rejected request.
+///
+/// Errors raised while processing a request carry their code as `anyhow` context, so the code
+/// can be recovered with [`ErrorCode::of`] once the error reaches the point where the request
+/// is marked as failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The reply email could not be parsed.
+    InvalidEmail,
+    /// The command in the reply does not match the command template.
+    InvalidCommand,
+    /// The DKIM signature of the reply does not verify.
+    DkimVerificationFailed,
+    /// The DKIM public key could not be registered.
+    DkimUpdateFailed,
+    /// The proof could not be generated.
+    ProvingFailed,
+    /// The on-chain transaction failed or was reverted.
+    TransactionFailed,
+    /// An email could not be sent.
+    EmailSendFailed,
+    /// The reply was not sent from the request's email address.
+    SenderMismatch,
+    /// A reply to the request was already received.
+    DuplicateReply,
+    /// No reply was received before the request expired.
+    Expired,
+    /// Any other error.
+    Internal,
+}
+
+impl ErrorCode {
+    /// Returns the code as stored in the `error_code` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidEmail => "INVALID_EMAIL",
+            ErrorCode::InvalidCommand => "INVALID_COMMAND",
+            ErrorCode::DkimVerificationFailed => "DKIM_VERIFICATION_FAILED",
+            ErrorCode::DkimUpdateFailed => "DKIM_UPDATE_FAILED",
+            ErrorCode::ProvingFailed => "PROVING_FAILED",
+            ErrorCode::TransactionFailed => "TRANSACTION_FAILED",
+            ErrorCode::EmailSendFailed => "EMAIL_SEND_FAILED",
+            ErrorCode::SenderMismatch => "SENDER_MISMATCH",
+            ErrorCode::DuplicateReply => "DUPLICATE_REPLY",
+            ErrorCode::Expired => "REQUEST_EXPIRED",
+            ErrorCode::Internal => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Recovers the code attached to an error, defaulting to `Internal`.
+    pub fn of(err: &Error) -> Self {
+        err.downcast_ref::<ErrorCode>()
+            .copied()
+            .unwrap_or(ErrorCode::Internal)
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    /// Formats the `ErrorCode` as a message for users.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let message = match self {
+            ErrorCode::InvalidEmail => "Invalid email",
+            ErrorCode::InvalidCommand => "Invalid command",
+            ErrorCode::DkimVerificationFailed => "The DKIM signature of the email does not verify",
+            ErrorCode::DkimUpdateFailed => "Failed to register the DKIM public key",
+            ErrorCode::ProvingFailed => "Failed to generate the proof",
+            ErrorCode::TransactionFailed => "Failed to submit the transaction",
+            ErrorCode::EmailSendFailed => "Failed to send the email",
+            ErrorCode::SenderMismatch => "Sender does not match the request's email address",
+            ErrorCode::DuplicateReply => "A reply to this request was already received",
+            ErrorCode::Expired => "The request expired before a reply was received",
+            ErrorCode::Internal => "Internal error",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+/// Represents the lifecycle of a queued job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "job_status_enum")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    /// The job ran out of attempts and will not be retried.
+    Dead,
+}
+
+/// Represents a job claimed by a worker.
+#[derive(Debug, FromRow)]
+pub struct JobModel {
+    /// The unique identifier for the job.
+    pub id: Uuid,
+    /// The request the job belongs to.
+    pub request_id: Uuid,
+    /// The work to perform.
+    pub payload: Json<Job>,
+    /// The number of times the job has been claimed, including the current attempt.
+    pub attempts: i32,
+    /// The number of attempts after which the job is dead-lettered.
+    pub max_attempts: i32,
+}
+
+/// Represents the progress of a job, as exposed in the request status.
+#[derive(Debug, FromRow, Deserialize, Serialize)]
+#[allow(non_snake_case)]
+pub struct JobSummaryModel {
+    /// The kind of work the job performs.
+    pub kind: String,
+    /// The current status of the job.
+    pub status: JobStatus,
+    /// The number of attempts made so far.
+    pub attempts: i32,
+    /// The number of attempts after which the job is dead-lettered.
+    #[serde(rename = "maxAttempts")]
+    pub max_attempts: i32,
+    /// The error of the last failed attempt.
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    /// The timestamp when the job is next due to run.
+    #[serde(rename = "runAt")]
+    pub run_at: Option<NaiveDateTime>,
+}
+
+impl RequestStatus {
+    /// Returns `true` if the request can no longer change its status.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            RequestStatus::Finished
+                | RequestStatus::TransactionFailed
+                | RequestStatus::Failed
+                | RequestStatus::Expired
+                | RequestStatus::Rejected
+        )
+    }
+}
+
+impl std::str::FromStr for RequestStatus {
+    type Err = Error;
+
+    /// Parses a `RequestStatus` from its `status_enum` value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let status = match s {
+            "Request received" => RequestStatus::RequestReceived,
+            "Email sent" => RequestStatus::EmailSent,
+            "Email response received" => RequestStatus::EmailResponseReceived,
+            "Proving" => RequestStatus::Proving,
+            "Performing on chain transaction" => RequestStatus::PerformingOnChainTransaction,
+            "Finished" => RequestStatus::Finished,
+            "Transaction failed" => RequestStatus::TransactionFailed,
+            "Failed" => RequestStatus::Failed,
+            "Expired" => RequestStatus::Expired,
+            "Rejected" => RequestStatus::Rejected,
+            _ => return Err(Error::msg(format!("Unknown request status: {}", s))),
+        };
+        Ok(status)
+    }
 }
 
 impl std::fmt::Display for RequestStatus {
@@ -81,15 +395,28 @@ impl From<sqlx::types::Json<EmailTxAuthSchema>> for EmailTxAuthSchema {
 ///
 /// * `pool` - A reference to the PostgreSQL connection pool.
 /// * `email_tx_auth` - A reference to the `EmailTxAuthSchema` to be inserted.
+/// * `ttl_secs` - The number of seconds the request waits for a reply before it expires.
+/// * `client_id` - The API client submitting the request, if auth is enabled.
 ///
 /// # Returns
 ///
 /// A `Result` containing the `Uuid` of the newly created request.
-pub async fn create_request(pool: &PgPool, email_tx_auth: &EmailTxAuthSchema) -> Result<Uuid> {
+pub async fn create_request(
+    pool: &PgPool,
+    email_tx_auth: &EmailTxAuthSchema,
+    ttl_secs: u64,
+    client_id: Option<Uuid>,
+) -> Result<Uuid> {
     // Assuming the database column is of type JSONB and can directly accept the struct
     let query_result = sqlx::query!(
-        "INSERT INTO requests (email_tx_auth) VALUES ($1) RETURNING id",
-        serde_json::to_value(email_tx_auth)? // Convert struct to JSON for insertion
+        r#"
+        INSERT INTO requests (email_tx_auth, expires_at, client_id)
+        VALUES ($1, NOW() + make_interval(secs => $2), $3)
+        RETURNING id
+        "#,
+        serde_json::to_value(email_tx_auth)?, // Convert struct to JSON for insertion
+        ttl_secs as f64,
+        client_id
     )
     .fetch_one(pool)
     .await?;
@@ -107,10 +434,19 @@ pub async fn create_request(pool: &PgPool, email_tx_auth: &EmailTxAuthSchema) ->
 ///
 /// # Returns
 ///
-/// A `Result` indicating success or failure.
-pub async fn update_request(pool: &PgPool, request_id: Uuid, status: RequestStatus) -> Result<()> {
-    sqlx::query!(
-        "UPDATE requests SET status = $1 WHERE id = $2",
+/// A `Result` containing `true` if the request was updated, or `false` if it had already
+/// reached a terminal state and was left unchanged.
+pub async fn update_request(
+    pool: &PgPool,
+    request_id: Uuid,
+    status: RequestStatus,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE requests SET status = $1
+        WHERE id = $2
+            AND status NOT IN ('Finished', 'Transaction failed', 'Failed', 'Expired', 'Rejected')
+        "#,
         status as RequestStatus,
         request_id
     )
@@ -118,7 +454,46 @@ pub async fn update_request(pool: &PgPool, request_id: Uuid, status: RequestStat
     .await
     .map_err(|e| Error::msg(format!("Failed to update request: {}", e)))?;
 
-    Ok(())
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Moves a request to a terminal failure state and records the error.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request to update.
+/// * `status` - The failure status to set for the request.
+/// * `error_code` - The code of the error that ended the request.
+/// * `error_message` - The message of the error that ended the request.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the request was updated, or `false` if it had already
+/// reached a terminal state and was left unchanged.
+pub async fn fail_request(
+    pool: &PgPool,
+    request_id: Uuid,
+    status: RequestStatus,
+    error_code: ErrorCode,
+    error_message: &str,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE requests SET status = $1, error_code = $2, error_message = $3
+        WHERE id = $4
+            AND status NOT IN ('Finished', 'Transaction failed', 'Failed', 'Expired', 'Rejected')
+        "#,
+        status as RequestStatus,
+        error_code.as_str(),
+        error_message,
+        request_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update request: {}", e)))?;
+
+    Ok(query_result.rows_affected() > 0)
 }
 
 /// Retrieves a request from the database by its unique identifier.
@@ -139,7 +514,12 @@ pub async fn get_request(pool: &PgPool, request_id: Uuid) -> Result<RequestModel
             id, 
             status as "status: RequestStatus", 
             updated_at::timestamp as "updated_at: NaiveDateTime",
-            email_tx_auth as "email_tx_auth: Json<EmailTxAuthSchema>"
+            email_tx_auth as "email_tx_auth: Json<EmailTxAuthSchema>",
+            error_code,
+            error_message,
+            expires_at::timestamp as "expires_at: NaiveDateTime",
+            group_id,
+            client_id
         FROM requests 
         WHERE id = $1
         "#,
@@ -179,3 +559,1459 @@ pub async fn insert_expected_reply(
 
     Ok(())
 }
+
+/// Records the outcome of an on-chain transaction for a request.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+/// * `tx_hash` - The hash of the transaction, if it was sent.
+/// * `receipt` - The receipt of the confirmed transaction.
+/// * `error` - The error that occurred while sending the transaction.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn insert_on_chain_transaction(
+    pool: &PgPool,
+    request_id: Uuid,
+    tx_hash: Option<String>,
+    receipt: Option<serde_json::Value>,
+    error: Option<String>,
+) -> Result<()> {
+    sqlx::query!(
+        "INSERT INTO on_chain_transactions (request_id, tx_hash, receipt, error) VALUES ($1, $2, $3, $4)",
+        request_id,
+        tx_hash,
+        receipt,
+        error
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to insert on_chain_transaction: {}", e)))?;
+
+    Ok(())
+}
+
+/// Records the outcome of a sent on-chain transaction.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+/// * `tx_hash` - The hash of the transaction.
+/// * `receipt` - The receipt of the confirmed transaction.
+/// * `error` - The error if the transaction reverted or was dropped.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn update_on_chain_transaction(
+    pool: &PgPool,
+    request_id: Uuid,
+    tx_hash: &str,
+    receipt: Option<serde_json::Value>,
+    error: Option<String>,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE on_chain_transactions SET receipt = $3, error = $4 WHERE request_id = $1 AND tx_hash = $2",
+        request_id,
+        tx_hash,
+        receipt,
+        error
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update on_chain_transaction: {}", e)))?;
+
+    Ok(())
+}
+
+/// Retrieves the latest on-chain transaction recorded for a request.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+///
+/// # Returns
+///
+/// A `Result` containing the `OnChainTransactionModel` if one was recorded.
+pub async fn get_on_chain_transaction(
+    pool: &PgPool,
+    request_id: Uuid,
+) -> Result<Option<OnChainTransactionModel>> {
+    let query_result = sqlx::query_as!(
+        OnChainTransactionModel,
+        r#"
+        SELECT
+            tx_hash,
+            receipt,
+            error,
+            created_at::timestamp as "created_at: NaiveDateTime"
+        FROM on_chain_transactions
+        WHERE request_id = $1
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        request_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Inserts a new pending job into the queue.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request the job belongs to.
+/// * `job` - The work to perform.
+/// * `max_attempts` - The number of attempts after which the job is dead-lettered.
+///
+/// # Returns
+///
+/// A `Result` containing the `Uuid` of the new job.
+pub async fn insert_job(
+    pool: &PgPool,
+    request_id: Uuid,
+    job: &Job,
+    max_attempts: i32,
+) -> Result<Uuid> {
+    let query_result = sqlx::query!(
+        "INSERT INTO jobs (request_id, kind, payload, max_attempts) VALUES ($1, $2, $3, $4) RETURNING id",
+        request_id,
+        job.kind(),
+        serde_json::to_value(job)?,
+        max_attempts
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to insert job: {}", e)))?;
+
+    Ok(query_result.id)
+}
+
+/// Claims the next due job, marking it as running and counting the attempt.
+///
+/// Running jobs whose lease has expired are claimed again, which resumes work left behind by
+/// a worker that crashed or a relayer that restarted.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `lease_secs` - The number of seconds after which a running job is considered abandoned.
+///
+/// # Returns
+///
+/// A `Result` containing the claimed `JobModel`, or `None` if no job is due.
+pub async fn claim_job(pool: &PgPool, lease_secs: u64) -> Result<Option<JobModel>> {
+    let query_result = sqlx::query_as!(
+        JobModel,
+        r#"
+        UPDATE jobs
+        SET status = 'Running', attempts = attempts + 1, locked_at = NOW(), updated_at = NOW()
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE (status = 'Pending' AND run_at <= NOW())
+                OR (status = 'Running' AND locked_at < NOW() - make_interval(secs => $1))
+            ORDER BY run_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING
+            id,
+            request_id,
+            payload as "payload: Json<Job>",
+            attempts,
+            max_attempts
+        "#,
+        lease_secs as f64
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Renews the lease of a running job, so it is not claimed again while its worker is alive.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `job_id` - The unique identifier of the job.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn renew_job_lease(pool: &PgPool, job_id: Uuid) -> Result<()> {
+    sqlx::query!(
+        "UPDATE jobs SET locked_at = NOW(), updated_at = NOW() WHERE id = $1 AND status = 'Running'",
+        job_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to renew job lease: {}", e)))?;
+
+    Ok(())
+}
+
+/// Marks a job as completed and enqueues its follow-up job in the same transaction.
+///
+/// Only a running job is completed. If another worker already completed the job after its
+/// lease expired, the follow-up job is not enqueued again.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `job_id` - The unique identifier of the job.
+/// * `follow_up` - The job to enqueue for the same request, with its maximum number of attempts.
+///
+/// # Returns
+///
+/// A `Result` containing the `Uuid` of the follow-up job, if one was enqueued.
+pub async fn complete_job(
+    pool: &PgPool,
+    job_id: Uuid,
+    follow_up: Option<(&Job, i32)>,
+) -> Result<Option<Uuid>> {
+    let mut tx = pool.begin().await?;
+
+    let completed = sqlx::query!(
+        r#"
+        UPDATE jobs SET status = 'Completed', locked_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND status = 'Running'
+        RETURNING request_id
+        "#,
+        job_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to complete job: {}", e)))?;
+
+    let follow_up_id = match (completed, follow_up) {
+        (Some(completed), Some((job, max_attempts))) => {
+            let query_result = sqlx::query!(
+                "INSERT INTO jobs (request_id, kind, payload, max_attempts) VALUES ($1, $2, $3, $4) RETURNING id",
+                completed.request_id,
+                job.kind(),
+                serde_json::to_value(job)?,
+                max_attempts
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|e| Error::msg(format!("Failed to insert job: {}", e)))?;
+            Some(query_result.id)
+        }
+        _ => None,
+    };
+
+    tx.commit().await?;
+    Ok(follow_up_id)
+}
+
+/// Records a failed attempt of a job, scheduling a retry or dead-lettering it.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `job` - The job that failed.
+/// * `error` - The error of the failed attempt.
+/// * `backoff_secs` - The delay before the job is retried.
+///
+/// # Returns
+///
+/// A `Result` containing the new status of the job.
+pub async fn fail_job(
+    pool: &PgPool,
+    job: &JobModel,
+    error: &str,
+    backoff_secs: u64,
+) -> Result<JobStatus> {
+    let status = if job.attempts >= job.max_attempts {
+        JobStatus::Dead
+    } else {
+        JobStatus::Pending
+    };
+
+    sqlx::query!(
+        r#"
+        UPDATE jobs
+        SET status = $1, last_error = $2, run_at = NOW() + make_interval(secs => $3),
+            locked_at = NULL, updated_at = NOW()
+        WHERE id = $4
+        "#,
+        status as JobStatus,
+        error,
+        backoff_secs as f64,
+        job.id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update job: {}", e)))?;
+
+    Ok(status)
+}
+
+/// Retrieves the jobs of a request in the order they were enqueued.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+///
+/// # Returns
+///
+/// A `Result` containing the jobs of the request.
+pub async fn get_jobs(pool: &PgPool, request_id: Uuid) -> Result<Vec<JobSummaryModel>> {
+    let query_result = sqlx::query_as!(
+        JobSummaryModel,
+        r#"
+        SELECT
+            kind,
+            status as "status: JobStatus",
+            attempts,
+            max_attempts,
+            last_error,
+            run_at::timestamp as "run_at: NaiveDateTime"
+        FROM jobs
+        WHERE request_id = $1
+        ORDER BY created_at
+        "#,
+        request_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Retrieves the expected reply matching one of the given message IDs.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `message_ids` - The message IDs an incoming email replies to.
+///
+/// # Returns
+///
+/// A `Result` containing the most recent matching `ExpectedReplyModel`, if any.
+pub async fn get_expected_reply(
+    pool: &PgPool,
+    message_ids: &[String],
+) -> Result<Option<ExpectedReplyModel>> {
+    if message_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let query_result = sqlx::query_as!(
+        ExpectedReplyModel,
+        r#"
+        SELECT
+            message_id,
+            request_id,
+            has_reply,
+            created_at as "created_at!: chrono::DateTime<chrono::Utc>"
+        FROM expected_replies
+        WHERE message_id = ANY($1)
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+        message_ids
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Marks the expected reply of a request as received.
+///
+/// The update only matches replies that have not been received yet, so concurrent deliveries of
+/// a reply cannot both succeed.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The request ID associated with the expected reply.
+/// * `reply_message_id` - The Message-ID of the reply.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if this is the first reply to the request.
+pub async fn mark_expected_reply(
+    pool: &PgPool,
+    request_id: &str,
+    reply_message_id: Option<&str>,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE expected_replies
+        SET has_reply = TRUE, reply_message_id = $2
+        WHERE request_id = $1 AND has_reply IS NOT TRUE
+        "#,
+        request_id,
+        reply_message_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update expected_reply: {}", e)))?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Releases the expected reply of a request whose reply could not be processed.
+///
+/// The request accepts a new reply afterwards, so the user can retry by replying again.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The request ID associated with the expected reply.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn release_expected_reply(pool: &PgPool, request_id: &str) -> Result<()> {
+    sqlx::query!(
+        "UPDATE expected_replies SET has_reply = FALSE, reply_message_id = NULL WHERE request_id = $1",
+        request_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update expected_reply: {}", e)))?;
+
+    Ok(())
+}
+
+/// Retrieves the Message-ID of the reply received for a request.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The request ID associated with the expected reply.
+///
+/// # Returns
+///
+/// A `Result` containing the Message-ID of the reply, if one was received.
+pub async fn get_reply_message_id(pool: &PgPool, request_id: &str) -> Result<Option<String>> {
+    let query_result = sqlx::query!(
+        "SELECT reply_message_id FROM expected_replies WHERE request_id = $1 AND has_reply = TRUE ORDER BY created_at, message_id LIMIT 1",
+        request_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result.and_then(|row| row.reply_message_id))
+}
+
+/// Marks a request as finished unless it already reached a terminal state.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request to update.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the request was finished by this call.
+pub async fn finish_request(pool: &PgPool, request_id: Uuid) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE requests SET status = $1
+        WHERE id = $2
+            AND status NOT IN ('Finished', 'Transaction failed', 'Failed', 'Expired', 'Rejected')
+        "#,
+        RequestStatus::Finished as RequestStatus,
+        request_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to update request: {}", e)))?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Creates a new API client with the hash of its API key.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `name` - The name of the client.
+/// * `key_hash` - The hash of the client's API key.
+///
+/// # Returns
+///
+/// A `Result` containing the `Uuid` of the new client.
+pub async fn insert_api_client(pool: &PgPool, name: &str, key_hash: &str) -> Result<Uuid> {
+    let query_result = sqlx::query!(
+        "INSERT INTO api_clients (name, key_hash) VALUES ($1, $2) RETURNING id",
+        name,
+        key_hash
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to insert api_client: {}", e)))?;
+
+    Ok(query_result.id)
+}
+
+/// Retrieves the active API client with the given API key hash.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key_hash` - The hash of the API key.
+///
+/// # Returns
+///
+/// A `Result` containing the `ApiClientModel`, or `None` if the key is unknown or revoked.
+pub async fn get_api_client_by_key_hash(
+    pool: &PgPool,
+    key_hash: &str,
+) -> Result<Option<ApiClientModel>> {
+    let query_result = sqlx::query_as!(
+        ApiClientModel,
+        r#"
+        SELECT
+            id,
+            name,
+            created_at::timestamp as "created_at: NaiveDateTime",
+            revoked_at::timestamp as "revoked_at: NaiveDateTime"
+        FROM api_clients
+        WHERE key_hash = $1 AND revoked_at IS NULL
+        "#,
+        key_hash
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Retrieves an active API client by its unique identifier.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `client_id` - The unique identifier of the client.
+///
+/// # Returns
+///
+/// A `Result` containing the `ApiClientModel`, or `None` if the client is unknown or revoked.
+pub async fn get_api_client(pool: &PgPool, client_id: Uuid) -> Result<Option<ApiClientModel>> {
+    let query_result = sqlx::query_as!(
+        ApiClientModel,
+        r#"
+        SELECT
+            id,
+            name,
+            created_at::timestamp as "created_at: NaiveDateTime",
+            revoked_at::timestamp as "revoked_at: NaiveDateTime"
+        FROM api_clients
+        WHERE id = $1 AND revoked_at IS NULL
+        "#,
+        client_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Retrieves all API clients, including revoked ones.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+///
+/// # Returns
+///
+/// A `Result` containing the API clients ordered by creation time.
+pub async fn get_api_clients(pool: &PgPool) -> Result<Vec<ApiClientModel>> {
+    let query_result = sqlx::query_as!(
+        ApiClientModel,
+        r#"
+        SELECT
+            id,
+            name,
+            created_at::timestamp as "created_at: NaiveDateTime",
+            revoked_at::timestamp as "revoked_at: NaiveDateTime"
+        FROM api_clients
+        ORDER BY created_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Revokes the API key of a client.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `client_id` - The unique identifier of the client.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if an active client was revoked.
+pub async fn revoke_api_client(pool: &PgPool, client_id: Uuid) -> Result<bool> {
+    let query_result = sqlx::query!(
+        "UPDATE api_clients SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+        client_id
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to revoke api_client: {}", e)))?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Counts hits against several rate limit keys in the given window, all or none of them.
+///
+/// The counters are incremented in a single transaction, which is rolled back if any of them
+/// exceeds its limit, so a rejected request does not use up the other limits.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `hits` - The rate limit keys, e.g. `client:<id>`, with the number of hits and their limit.
+/// * `window_start` - The start of the current fixed window.
+///
+/// # Returns
+///
+/// A `Result` containing the index in `hits` of the first exceeded limit and its count including
+/// the hits, or `None` if all hits were counted.
+pub async fn hit_rate_limits(
+    pool: &PgPool,
+    hits: &[(String, i32, i32)],
+    window_start: chrono::DateTime<chrono::Utc>,
+) -> Result<Option<(usize, i32)>> {
+    let mut tx = pool.begin().await?;
+
+    for (idx, (key, count, limit)) in hits.iter().enumerate() {
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO rate_limits (key, window_start, count)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (key, window_start) DO UPDATE SET count = rate_limits.count + $3
+            RETURNING count
+            "#,
+            key,
+            window_start,
+            count
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| Error::msg(format!("Failed to update rate limit: {}", e)))?;
+
+        if query_result.count > *limit {
+            tx.rollback().await?;
+            return Ok(Some((idx, query_result.count)));
+        }
+    }
+
+    tx.commit().await?;
+    Ok(None)
+}
+
+/// Deletes the rate limit counters of windows that have ended.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `window_start` - The start of the current fixed window.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn prune_rate_limits(
+    pool: &PgPool,
+    window_start: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM rate_limits WHERE window_start < $1",
+        window_start
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Claims the requests whose reminder is due, so each reminder is sent once.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `reminder_before_secs` - The number of seconds before expiry at which the reminder is due.
+///
+/// # Returns
+///
+/// A `Result` containing the requests to remind.
+pub async fn claim_request_reminders(
+    pool: &PgPool,
+    reminder_before_secs: u64,
+) -> Result<Vec<RequestModel>> {
+    let query_result = sqlx::query_as!(
+        RequestModel,
+        r#"
+        UPDATE requests
+        SET reminder_sent_at = NOW()
+        WHERE status = 'Email sent'
+            AND reminder_sent_at IS NULL
+            AND expires_at > NOW()
+            AND expires_at <= NOW() + make_interval(secs => $1)
+        RETURNING
+            id,
+            status as "status: RequestStatus",
+            updated_at::timestamp as "updated_at: NaiveDateTime",
+            email_tx_auth as "email_tx_auth: Json<EmailTxAuthSchema>",
+            error_code,
+            error_message,
+            expires_at::timestamp as "expires_at: NaiveDateTime",
+            group_id,
+            client_id
+        "#,
+        reminder_before_secs as f64
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Moves the requests still waiting for a reply past their expiry to the expired state.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+///
+/// # Returns
+///
+/// A `Result` containing the requests that expired.
+pub async fn expire_requests(pool: &PgPool) -> Result<Vec<RequestModel>> {
+    let query_result = sqlx::query_as!(
+        RequestModel,
+        r#"
+        UPDATE requests
+        SET status = 'Expired', error_code = $1, error_message = $2
+        WHERE status = 'Email sent' AND expires_at <= NOW()
+        RETURNING
+            id,
+            status as "status: RequestStatus",
+            updated_at::timestamp as "updated_at: NaiveDateTime",
+            email_tx_auth as "email_tx_auth: Json<EmailTxAuthSchema>",
+            error_code,
+            error_message,
+            expires_at::timestamp as "expires_at: NaiveDateTime",
+            group_id,
+            client_id
+        "#,
+        ErrorCode::Expired.as_str(),
+        ErrorCode::Expired.to_string()
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Retrieves the email auth message generated for a request.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+///
+/// # Returns
+///
+/// A `Result` containing the serialized `EmailAuthMsg`, if one was generated.
+pub async fn get_email_auth_msg(
+    pool: &PgPool,
+    request_id: Uuid,
+) -> Result<Option<serde_json::Value>> {
+    let query_result = sqlx::query!(
+        "SELECT response FROM email_auth_messages WHERE request_id = $1 ORDER BY created_at LIMIT 1",
+        request_id.to_string()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result.map(|row| row.response))
+}
+
+/// Retrieves the ABI-encoded email auth message stored for a request or an email nullifier.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+/// * `email_nullifier` - The hex-encoded email nullifier of a generated message, if known.
+///
+/// # Returns
+///
+/// A `Result` containing the ABI-encoded `EmailAuthMsg` stored first, if any.
+pub async fn get_encoded_email_auth_msg(
+    pool: &PgPool,
+    request_id: Uuid,
+    email_nullifier: Option<&str>,
+) -> Result<Option<Vec<u8>>> {
+    let query_result = sqlx::query!(
+        r#"
+        SELECT encoded_msg as "encoded_msg!"
+        FROM email_auth_messages
+        WHERE (request_id = $1 OR email_nullifier = $2) AND encoded_msg IS NOT NULL
+        ORDER BY created_at
+        LIMIT 1
+        "#,
+        request_id.to_string(),
+        email_nullifier
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result.map(|row| row.encoded_msg))
+}
+
+/// Records an attempt to deliver a webhook callback.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `job` - The job delivering the callback.
+/// * `url` - The URL the callback was posted to.
+/// * `status` - The request status the callback reported.
+/// * `response_status` - The HTTP status returned by the callback URL, if it responded.
+/// * `error` - The error of a failed delivery.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn insert_webhook_delivery(
+    pool: &PgPool,
+    job: &JobModel,
+    url: &str,
+    status: RequestStatus,
+    response_status: Option<i32>,
+    error: Option<String>,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO webhook_deliveries
+            (request_id, job_id, url, status, attempt, response_status, error)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        job.request_id,
+        job.id,
+        url,
+        status as RequestStatus,
+        job.attempts,
+        response_status,
+        error
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| Error::msg(format!("Failed to insert webhook_delivery: {}", e)))?;
+
+    Ok(())
+}
+
+/// Retrieves the webhook delivery attempts of a request in the order they were made.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `request_id` - The unique identifier of the request.
+///
+/// # Returns
+///
+/// A `Result` containing the delivery attempts of the request.
+pub async fn get_webhook_deliveries(
+    pool: &PgPool,
+    request_id: Uuid,
+) -> Result<Vec<WebhookDeliveryModel>> {
+    let query_result = sqlx::query_as!(
+        WebhookDeliveryModel,
+        r#"
+        SELECT
+            url,
+            status as "status: RequestStatus",
+            attempt,
+            response_status,
+            error,
+            created_at::timestamp as "created_at: NaiveDateTime"
+        FROM webhook_deliveries
+        WHERE request_id = $1
+        ORDER BY created_at
+        "#,
+        request_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Creates a group of requests in a single transaction.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `requests` - The requests of the group.
+/// * `default_ttl_secs` - The TTL of requests that do not set their own.
+/// * `threshold` - The number of proven replies after which the group is complete.
+/// * `client_id` - The API client submitting the group, if auth is enabled.
+///
+/// # Returns
+///
+/// A `Result` containing the `Uuid` of the group and of its requests, in order.
+pub async fn create_request_group(
+    pool: &PgPool,
+    requests: &[EmailTxAuthSchema],
+    default_ttl_secs: u64,
+    threshold: i32,
+    client_id: Option<Uuid>,
+) -> Result<(Uuid, Vec<Uuid>)> {
+    let mut tx = pool.begin().await?;
+
+    let group = sqlx::query!(
+        "INSERT INTO request_groups (threshold, client_id) VALUES ($1, $2) RETURNING id",
+        threshold,
+        client_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let mut request_ids = Vec::with_capacity(requests.len());
+    for email_tx_auth in requests {
+        let ttl_secs = email_tx_auth.ttl_secs.unwrap_or(default_ttl_secs);
+        let query_result = sqlx::query!(
+            r#"
+            INSERT INTO requests (email_tx_auth, expires_at, group_id, client_id)
+            VALUES ($1, NOW() + make_interval(secs => $2), $3, $4)
+            RETURNING id
+            "#,
+            serde_json::to_value(email_tx_auth)?,
+            ttl_secs as f64,
+            group.id,
+            client_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        request_ids.push(query_result.id);
+    }
+
+    tx.commit().await?;
+    Ok((group.id, request_ids))
+}
+
+/// Retrieves a group of requests by its unique identifier.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `group_id` - The unique identifier of the group.
+///
+/// # Returns
+///
+/// A `Result` containing the `RequestGroupModel`, if found.
+pub async fn get_request_group(pool: &PgPool, group_id: Uuid) -> Result<Option<RequestGroupModel>> {
+    let query_result = sqlx::query_as!(
+        RequestGroupModel,
+        r#"
+        SELECT
+            id,
+            threshold,
+            created_at::timestamp as "created_at: NaiveDateTime",
+            client_id
+        FROM request_groups
+        WHERE id = $1
+        "#,
+        group_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Retrieves the requests of a group with whether their reply has been proven.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `group_id` - The unique identifier of the group.
+///
+/// # Returns
+///
+/// A `Result` containing the requests of the group.
+pub async fn get_group_requests(pool: &PgPool, group_id: Uuid) -> Result<Vec<GroupRequestModel>> {
+    let query_result = sqlx::query_as!(
+        GroupRequestModel,
+        r#"
+        SELECT
+            id,
+            status as "status: RequestStatus",
+            email_tx_auth->>'emailAddress' as email_address,
+            EXISTS (
+                SELECT 1 FROM email_auth_messages WHERE request_id = requests.id::text
+            ) as "proven!",
+            error_code
+        FROM requests
+        WHERE group_id = $1
+        ORDER BY created_at, id
+        "#,
+        group_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Claims a message of an IMAP mailbox for processing.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `mailbox` - The name of the mailbox the message is in.
+/// * `uid_validity` - The UIDVALIDITY of the mailbox, which scopes its UIDs.
+/// * `uid` - The UID of the message.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the message had not been processed before.
+pub async fn claim_imap_message(
+    pool: &PgPool,
+    mailbox: &str,
+    uid_validity: u32,
+    uid: u32,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        INSERT INTO imap_processed_messages (mailbox, uid_validity, uid)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING
+        "#,
+        mailbox,
+        uid_validity as i64,
+        uid as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Releases the claim on an IMAP message whose processing failed temporarily.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `mailbox` - The name of the mailbox the message is in.
+/// * `uid_validity` - The UIDVALIDITY of the mailbox, which scopes its UIDs.
+/// * `uid` - The UID of the message.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn release_imap_message(
+    pool: &PgPool,
+    mailbox: &str,
+    uid_validity: u32,
+    uid: u32,
+) -> Result<()> {
+    sqlx::query!(
+        "DELETE FROM imap_processed_messages WHERE mailbox = $1 AND uid_validity = $2 AND uid = $3",
+        mailbox,
+        uid_validity as i64,
+        uid as i64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves which of the given IMAP messages were already claimed.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `mailbox` - The name of the mailbox the messages are in.
+/// * `uid_validity` - The UIDVALIDITY of the mailbox, which scopes its UIDs.
+/// * `uids` - The UIDs of the messages.
+///
+/// # Returns
+///
+/// A `Result` containing the UIDs of the claimed messages.
+pub async fn get_claimed_imap_uids(
+    pool: &PgPool,
+    mailbox: &str,
+    uid_validity: u32,
+    uids: &[u32],
+) -> Result<Vec<u32>> {
+    let uids: Vec<i64> = uids.iter().map(|&uid| uid as i64).collect();
+    let query_result = sqlx::query!(
+        r#"
+        SELECT uid FROM imap_processed_messages
+        WHERE mailbox = $1 AND uid_validity = $2 AND uid = ANY($3)
+        "#,
+        mailbox,
+        uid_validity as i64,
+        &uids
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result.into_iter().map(|row| row.uid as u32).collect())
+}
+
+/// Records a use of a DKIM key if it is cached as registered.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+/// * `ttl_secs` - The number of seconds a registered key is trusted without checking the registry.
+/// * `hits` - The number of uses to record.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the key was registered when the registry was last checked,
+/// no more than `ttl_secs` ago, and has not been flagged as revoked since.
+pub async fn touch_cached_dkim_key(
+    pool: &PgPool,
+    key: &DkimKeyModel,
+    ttl_secs: u64,
+    hits: i32,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE dkim_keys
+        SET hits = hits + $6
+        WHERE chain = $1 AND registry_address = $2 AND domain = $3 AND selector = $4
+            AND public_key_hash = $5 AND registered AND flagged_at IS NULL
+            AND checked_at > NOW() - make_interval(secs => $7)
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash,
+        hits,
+        ttl_secs as f64
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(query_result.rows_affected() > 0)
+}
+
+/// Saves the registration status of a DKIM key as checked in the registry.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+/// * `registered` - Whether the key is registered.
+/// * `hits` - The number of uses to record.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn upsert_dkim_key(
+    pool: &PgPool,
+    key: &DkimKeyModel,
+    registered: bool,
+    hits: i32,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        INSERT INTO dkim_keys
+            (chain, registry_address, domain, selector, public_key_hash, registered, hits)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (chain, registry_address, domain, selector, public_key_hash) DO UPDATE
+        SET registered = EXCLUDED.registered, hits = dkim_keys.hits + EXCLUDED.hits,
+            checked_at = NOW()
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash,
+        registered,
+        hits
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the most used DKIM selectors.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `limit` - The maximum number of selectors to return.
+///
+/// # Returns
+///
+/// A `Result` containing the domains and selectors, most used first.
+pub async fn get_popular_dkim_selectors(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<(String, String)>> {
+    let query_result = sqlx::query!(
+        r#"
+        SELECT domain, selector
+        FROM dkim_keys
+        GROUP BY domain, selector
+        ORDER BY SUM(hits) DESC
+        LIMIT $1
+        "#,
+        limit
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result
+        .into_iter()
+        .map(|row| (row.domain, row.selector))
+        .collect())
+}
+
+/// Retrieves the registered DKIM keys of the domains requests were sent to.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+///
+/// # Returns
+///
+/// A `Result` containing the keys not confirmed revoked yet.
+pub async fn get_dkim_keys_to_monitor(pool: &PgPool) -> Result<Vec<DkimKeyModel>> {
+    let query_result = sqlx::query_as!(
+        DkimKeyModel,
+        r#"
+        SELECT chain, registry_address, domain, selector, public_key_hash
+        FROM dkim_keys
+        WHERE registered AND revoked_at IS NULL
+            AND lower(domain) IN (
+                SELECT DISTINCT lower(split_part(email_tx_auth->>'emailAddress', '@', 2))
+                FROM requests
+            )
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Flags a DKIM key its domain no longer publishes, without considering it revoked.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+/// * `reason` - Why the key is flagged.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn flag_dkim_key(pool: &PgPool, key: &DkimKeyModel, reason: &str) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE dkim_keys
+        SET flagged_at = COALESCE(flagged_at, NOW()), revocation_reason = $6, empty_key_checks = 0
+        WHERE chain = $1 AND registry_address = $2 AND domain = $3 AND selector = $4
+            AND public_key_hash = $5 AND revoked_at IS NULL
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash,
+        reason
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a check in which the selector of a DKIM key published an empty key, and marks the
+/// key revoked once enough consecutive checks did.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+/// * `reason` - Why the key is flagged.
+/// * `confirmations` - The number of consecutive checks that confirm the revocation.
+///
+/// # Returns
+///
+/// A `Result` containing `true` if the key is now confirmed revoked.
+pub async fn record_empty_dkim_key(
+    pool: &PgPool,
+    key: &DkimKeyModel,
+    reason: &str,
+    confirmations: i32,
+) -> Result<bool> {
+    let query_result = sqlx::query!(
+        r#"
+        UPDATE dkim_keys
+        SET flagged_at = COALESCE(flagged_at, NOW()),
+            revocation_reason = $6,
+            empty_key_checks = empty_key_checks + 1,
+            revoked_at = CASE WHEN empty_key_checks + 1 >= $7 THEN NOW() END
+        WHERE chain = $1 AND registry_address = $2 AND domain = $3 AND selector = $4
+            AND public_key_hash = $5 AND revoked_at IS NULL
+        RETURNING revoked_at IS NOT NULL as "revoked!"
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash,
+        reason,
+        confirmations
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(query_result.is_some_and(|row| row.revoked))
+}
+
+/// Clears the flag of a DKIM key its domain publishes again.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn clear_dkim_key_flag(pool: &PgPool, key: &DkimKeyModel) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE dkim_keys
+        SET flagged_at = NULL, revocation_reason = NULL, empty_key_checks = 0
+        WHERE chain = $1 AND registry_address = $2 AND domain = $3 AND selector = $4
+            AND public_key_hash = $5 AND flagged_at IS NOT NULL AND revoked_at IS NULL
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the DKIM keys confirmed revoked whose revocation was not submitted yet.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+///
+/// # Returns
+///
+/// A `Result` containing the keys to revoke in their registries.
+pub async fn get_dkim_keys_to_revoke(pool: &PgPool) -> Result<Vec<DkimKeyModel>> {
+    let query_result = sqlx::query_as!(
+        DkimKeyModel,
+        r#"
+        SELECT chain, registry_address, domain, selector, public_key_hash
+        FROM dkim_keys
+        WHERE registered AND revoked_at IS NOT NULL AND revocation_tx_hash IS NULL
+        ORDER BY revoked_at
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
+
+/// Records the revocation of a DKIM key in its registry.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+/// * `key` - The key in the registry of a chain.
+/// * `tx_hash` - The hash of the revocation transaction.
+///
+/// # Returns
+///
+/// A `Result` indicating success or failure.
+pub async fn set_dkim_key_revocation_tx(
+    pool: &PgPool,
+    key: &DkimKeyModel,
+    tx_hash: &str,
+) -> Result<()> {
+    sqlx::query!(
+        r#"
+        UPDATE dkim_keys
+        SET registered = FALSE, revocation_tx_hash = $6, checked_at = NOW()
+        WHERE chain = $1 AND registry_address = $2 AND domain = $3 AND selector = $4
+            AND public_key_hash = $5
+        "#,
+        key.chain,
+        key.registry_address,
+        key.domain,
+        key.selector,
+        key.public_key_hash,
+        tx_hash
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Retrieves the flagged DKIM keys, including the ones confirmed revoked.
+///
+/// # Arguments
+///
+/// * `pool` - A reference to the PostgreSQL connection pool.
+///
+/// # Returns
+///
+/// A `Result` containing the flagged keys, most recently flagged first.
+pub async fn get_revoked_dkim_keys(pool: &PgPool) -> Result<Vec<RevokedDkimKeyModel>> {
+    let query_result = sqlx::query_as!(
+        RevokedDkimKeyModel,
+        r#"
+        SELECT
+            chain,
+            registry_address,
+            domain,
+            selector,
+            public_key_hash,
+            revocation_reason as reason,
+            flagged_at::timestamp as "flagged_at: NaiveDateTime",
+            revoked_at::timestamp as "revoked_at: NaiveDateTime",
+            revocation_tx_hash
+        FROM dkim_keys
+        WHERE flagged_at IS NOT NULL
+        ORDER BY flagged_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(query_result)
+}
@@ -1,18 +1,133 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use ethers::utils::hex;
 use relayer_utils::{
-    generate_email_circuit_input, generate_proof, u256_to_bytes32, EmailCircuitParams, ParsedEmail,
+    extract_email_addr_idxes, extract_invitation_code_idxes, extract_subject_all_idxes,
+    generate_email_circuit_input, u256_to_bytes32, AccountCode, EmailCircuitParams, ParsedEmail,
     LOG,
 };
+use serde::Serialize;
+use serde_json::Value;
 use slog::info;
 
 use crate::{
     abis::EmailProof,
     command::get_masked_command,
-    constants::{COMMAND_FIELDS, DOMAIN_FIELDS, SHA_PRECOMPUTE_SELECTOR},
+    config::{CircuitProfileConfig, Config},
+    constants::{DEFAULT_CIRCUIT_PROFILE, SHA_PRECOMPUTE_SELECTOR},
     model::{update_request, RequestModel, RequestStatus},
+    prover::create_prover,
+    schema::EmailTxAuthSchema,
+    webhook::notify_status,
     RelayerState,
 };
 
+/// The positions of the values in the public signals of a circuit.
+///
+/// The public signals are laid out as the domain name, the public key hash, the email nullifier,
+/// the timestamp, the masked command, the account salt and the flag for an existing account code.
+/// Recipient-bound circuits append the flag for a recipient in the command and its commitment.
+#[derive(Debug, Clone, Copy)]
+pub struct PublicSignalOffsets {
+    pub public_key_hash: usize,
+    pub email_nullifier: usize,
+    pub timestamp: usize,
+    pub masked_command: usize,
+    pub account_salt: usize,
+    pub is_code_exist: usize,
+    pub has_email_recipient: Option<usize>,
+    pub recipient_email_addr_commit: Option<usize>,
+}
+
+/// The commitment to the recipient email address in the command of a recipient-bound proof.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipientCommitment {
+    /// Indicates whether the command contains an email address.
+    pub has_email_recipient: bool,
+    /// The commitment to the email address, or zero if the command contains none.
+    pub recipient_email_addr_commit: String,
+}
+
+impl PublicSignalOffsets {
+    /// Derives the offsets from the field counts of a circuit profile.
+    ///
+    /// # Arguments
+    ///
+    /// * `profile` - The `CircuitProfileConfig` of the circuit.
+    /// * `recipient_bound` - Whether the circuit exposes the recipient commitment.
+    ///
+    /// # Returns
+    ///
+    /// The `PublicSignalOffsets` of the circuit.
+    pub fn from_profile(profile: &CircuitProfileConfig, recipient_bound: bool) -> Self {
+        let domain_fields = profile.domain_fields;
+        let command_fields = profile.command_fields;
+        let is_code_exist = domain_fields + command_fields + 4;
+        Self {
+            public_key_hash: domain_fields,
+            email_nullifier: domain_fields + 1,
+            timestamp: domain_fields + 2,
+            masked_command: domain_fields + 3,
+            account_salt: domain_fields + command_fields + 3,
+            is_code_exist,
+            has_email_recipient: recipient_bound.then_some(is_code_exist + 1),
+            recipient_email_addr_commit: recipient_bound.then_some(is_code_exist + 2),
+        }
+    }
+
+    /// Returns the number of public signals the circuit outputs.
+    pub fn signal_count(&self) -> usize {
+        self.recipient_email_addr_commit
+            .unwrap_or(self.is_code_exist)
+            + 1
+    }
+}
+
+/// Gets a circuit profile by its name.
+///
+/// Requests without a profile use the `default` profile, which falls back to the limits of the
+/// `email_auth` circuit if it is not configured.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the relayer.
+/// * `name` - The name of the profile, if the request selects one.
+///
+/// # Returns
+///
+/// A `Result` containing the `CircuitProfileConfig` or an error if no such profile exists.
+pub fn get_circuit_profile(config: &Config, name: Option<&str>) -> Result<CircuitProfileConfig> {
+    let name = name.unwrap_or(DEFAULT_CIRCUIT_PROFILE);
+    if let Some(profile) = config
+        .circuit_profiles
+        .iter()
+        .find(|profile| profile.name == name)
+    {
+        return Ok(profile.clone());
+    }
+
+    if name == DEFAULT_CIRCUIT_PROFILE {
+        Ok(CircuitProfileConfig::default())
+    } else {
+        Err(anyhow!("Unknown circuit profile: {}", name))
+    }
+}
+
+/// Checks whether a request is proven with a legacy circuit reading the command from the subject.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the relayer.
+/// * `email_tx_auth` - The `EmailTxAuthSchema` of the request.
+///
+/// # Returns
+///
+/// `true` if the circuit profile of the request is a legacy one.
+pub fn is_legacy_request(config: &Config, email_tx_auth: &EmailTxAuthSchema) -> bool {
+    get_circuit_profile(config, email_tx_auth.circuit_profile.as_deref())
+        .is_ok_and(|profile| profile.legacy)
+}
+
 /// Generates the email proof for authentication.
 ///
 /// This asynchronous function updates the request status, parses the email, generates the circuit input,
@@ -27,62 +142,277 @@ use crate::{
 /// # Returns
 ///
 /// A `Result` containing:
-/// - `Ok`: An `EmailProof` with the generated proof and account salt.
+/// - `Ok`: An `EmailProof` with the generated proof and account salt, and the
+///   `RecipientCommitment` if the request asked for a recipient-bound proof.
 /// - `Err`: An error if any step in the process fails.
 pub async fn generate_email_proof(
     email: &str,
     request: RequestModel,
     relayer_state: RelayerState,
-) -> Result<EmailProof> {
+) -> Result<(EmailProof, Option<RecipientCommitment>)> {
     // Update the request status to "Proving" in the database
-    update_request(&relayer_state.db, request.id, RequestStatus::Proving).await?;
+    if !update_request(&relayer_state.db, request.id, RequestStatus::Proving).await? {
+        bail!("Request {} has already ended", request.id);
+    }
+    notify_status(&relayer_state, request.id, RequestStatus::Proving).await;
 
     // Parse the email from the raw content
     let parsed_email = ParsedEmail::new_from_raw_email(email).await?;
 
+    // Resolve the circuit the request is proven with
+    let profile = get_circuit_profile(
+        &relayer_state.config,
+        request.email_tx_auth.circuit_profile.as_deref(),
+    )?;
+    let recipient_bound = request.email_tx_auth.recipient_bound;
+    let offsets = PublicSignalOffsets::from_profile(&profile, recipient_bound);
+    let circuit = if recipient_bound {
+        &profile.recipient_circuit
+    } else {
+        &profile.circuit
+    };
+
     // Generate the circuit input for the email proof
-    let circuit_input = generate_email_circuit_input(
-        email,
-        &request.email_tx_auth.account_code,
-        Some(EmailCircuitParams {
-            max_header_length: Some(1024),
-            max_body_length: Some(1024),
-            sha_precompute_selector: Some(SHA_PRECOMPUTE_SELECTOR.to_string()),
-            ignore_body_hash_check: Some(false),
-        }),
-    )
-    .await?;
+    let circuit_input = if profile.legacy {
+        generate_legacy_circuit_input(
+            email,
+            &parsed_email,
+            &request.email_tx_auth.account_code,
+            profile.max_header_length,
+        )
+        .await?
+    } else {
+        generate_email_circuit_input(
+            email,
+            &request.email_tx_auth.account_code,
+            Some(EmailCircuitParams {
+                max_header_length: Some(profile.max_header_length),
+                max_body_length: Some(profile.max_body_length),
+                sha_precompute_selector: Some(SHA_PRECOMPUTE_SELECTOR.to_string()),
+                ignore_body_hash_check: Some(profile.ignore_body_hash_check),
+            }),
+        )
+        .await?
+    };
+
+    // Recipient-bound circuits also take the position of the email address in the command
+    let circuit_input = if recipient_bound {
+        let command = parsed_email.get_command(profile.ignore_body_hash_check)?;
+        add_recipient_input(&circuit_input, &command)?
+    } else {
+        circuit_input
+    };
 
     // Generate the proof and public signals using the circuit input
-    let (proof, public_signals) = generate_proof(
-        &circuit_input,
-        "email_auth",
-        &relayer_state.config.prover_url,
-    )
-    .await?;
+    let (proof, public_signals) = create_prover(&relayer_state, &profile)
+        .prove(&circuit_input, circuit)
+        .await?;
 
     // Log the public signals for debugging purposes
     info!(LOG, "Public signals: {:?}", public_signals);
 
+    if public_signals.len() < offsets.signal_count() {
+        return Err(anyhow!(
+            "Circuit {} returned {} public signals, profile {} expects {}",
+            circuit,
+            public_signals.len(),
+            profile.name,
+            offsets.signal_count()
+        ));
+    }
+
     // Extract the account salt from the public signals
-    let account_salt = u256_to_bytes32(&public_signals[COMMAND_FIELDS + DOMAIN_FIELDS + 3]);
+    let account_salt = u256_to_bytes32(&public_signals[offsets.account_salt]);
     // Determine if the code exists based on the public signals
-    let is_code_exist = public_signals[COMMAND_FIELDS + DOMAIN_FIELDS + 4] == 1u8.into();
+    let is_code_exist = public_signals[offsets.is_code_exist] == 1u8.into();
     // Get the masked command from the public signals
-    let masked_command = get_masked_command(public_signals.clone(), DOMAIN_FIELDS + 3)?;
+    let masked_command = get_masked_command(
+        public_signals.clone(),
+        offsets.masked_command,
+        profile.command_fields,
+    )?;
 
     // Construct the email proof with the generated data
     let email_proof = EmailProof {
         proof,
         domain_name: parsed_email.get_email_domain()?,
-        public_key_hash: u256_to_bytes32(&public_signals[DOMAIN_FIELDS]),
-        timestamp: u256_to_bytes32(&public_signals[DOMAIN_FIELDS + 2]).into(),
+        public_key_hash: u256_to_bytes32(&public_signals[offsets.public_key_hash]),
+        timestamp: u256_to_bytes32(&public_signals[offsets.timestamp]).into(),
         masked_command,
-        email_nullifier: u256_to_bytes32(&public_signals[DOMAIN_FIELDS + 1]),
+        email_nullifier: u256_to_bytes32(&public_signals[offsets.email_nullifier]),
         account_salt,
         is_code_exist,
     };
 
+    // Decode the recipient commitment of recipient-bound proofs
+    let recipient = match (
+        offsets.has_email_recipient,
+        offsets.recipient_email_addr_commit,
+    ) {
+        (Some(has_email_recipient), Some(recipient_email_addr_commit)) => {
+            Some(RecipientCommitment {
+                has_email_recipient: public_signals[has_email_recipient] == 1u8.into(),
+                recipient_email_addr_commit: format!(
+                    "0x{}",
+                    hex::encode(u256_to_bytes32(
+                        &public_signals[recipient_email_addr_commit]
+                    ))
+                ),
+            })
+        }
+        _ => None,
+    };
+
     // Return the constructed email proof
-    Ok(email_proof)
+    Ok((email_proof, recipient))
+}
+
+/// Generates the circuit input of the legacy circuit, which reads the command from the subject.
+///
+/// # Arguments
+///
+/// * `email` - The raw email content as a `&str`.
+/// * `parsed_email` - The parsed email.
+/// * `account_code` - The account code of the request.
+/// * `max_header_length` - The maximum length of the header the circuit takes.
+///
+/// # Returns
+///
+/// A `Result` containing the circuit input of the legacy circuit as JSON.
+async fn generate_legacy_circuit_input(
+    email: &str,
+    parsed_email: &ParsedEmail,
+    account_code: &AccountCode,
+    max_header_length: usize,
+) -> Result<String> {
+    // The legacy circuit only reads the header, so the body is not hashed
+    let circuit_input = generate_email_circuit_input(
+        email,
+        account_code,
+        Some(EmailCircuitParams {
+            max_header_length: Some(max_header_length),
+            max_body_length: None,
+            sha_precompute_selector: None,
+            ignore_body_hash_check: Some(true),
+        }),
+    )
+    .await?;
+    to_legacy_input(&circuit_input, &parsed_email.canonicalized_header)
+}
+
+/// Converts the circuit input of the `email_auth` circuit into the one of the legacy circuit.
+///
+/// The legacy circuit takes the same header inputs and none of the body inputs. The subject and
+/// the account code in it are located in the header.
+///
+/// # Arguments
+///
+/// * `circuit_input` - The circuit input of the `email_auth` circuit as JSON.
+/// * `canonicalized_header` - The canonicalized header the circuit input was generated from.
+///
+/// # Returns
+///
+/// A `Result` containing the circuit input of the legacy circuit as JSON.
+fn to_legacy_input(circuit_input: &str, canonicalized_header: &str) -> Result<String> {
+    let subject_idx = extract_subject_all_idxes(canonicalized_header)?
+        .first()
+        .map(|(start, _)| *start)
+        .ok_or_else(|| anyhow!("Email has no subject"))?;
+    // The index is zero if the subject contains no account code, which the circuit ignores
+    let code_idx = extract_invitation_code_idxes(canonicalized_header)
+        .ok()
+        .and_then(|idxes| idxes.first().map(|(start, _)| *start))
+        .unwrap_or(0);
+
+    let mut circuit_input: Value = serde_json::from_str(circuit_input)?;
+    let fields = circuit_input
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Circuit input is not a JSON object"))?;
+    for field in [
+        "body_hash_idx",
+        "precomputed_sha",
+        "padded_body",
+        "padded_body_len",
+        "command_idx",
+        "padded_cleaned_body",
+    ] {
+        fields.remove(field);
+    }
+    fields.insert("subject_idx".to_string(), subject_idx.into());
+    fields.insert("code_idx".to_string(), code_idx.into());
+    Ok(circuit_input.to_string())
+}
+
+/// Adds the index of the recipient email address in the command to a circuit input.
+///
+/// The index is zero if the command contains no email address, in which case the circuit
+/// commits to nothing.
+///
+/// # Arguments
+///
+/// * `circuit_input` - The circuit input of the `email_auth` circuit as JSON.
+/// * `command` - The command extracted from the email body.
+///
+/// # Returns
+///
+/// A `Result` containing the circuit input of the recipient-bound circuit as JSON.
+fn add_recipient_input(circuit_input: &str, command: &str) -> Result<String> {
+    let mut circuit_input: Value = serde_json::from_str(circuit_input)?;
+    let command_email_addr_idx = extract_email_addr_idxes(command)
+        .ok()
+        .and_then(|idxes| idxes.first().map(|(start, _)| *start))
+        .unwrap_or(0);
+
+    circuit_input
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("Circuit input is not a JSON object"))?
+        .insert(
+            "command_email_addr_idx".to_string(),
+            command_email_addr_idx.into(),
+        );
+    Ok(circuit_input.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A legacy reply whose subject contains an email address and the account code.
+    const LEGACY_EMAIL: &str =
+        include_str!("../../circuits/tests/emails/email_auth_legacy_test5.eml");
+    const ACCOUNT_CODE: &str = "01eb9b204cc24c3baee11accc37d253a9c53e92b1a2cc07763475c135d575b76";
+
+    #[tokio::test]
+    #[ignore = "parsing the email looks up the DKIM key of gmail.com in DNS"]
+    async fn legacy_circuit_input_locates_subject_and_code_in_header() {
+        let parsed_email = ParsedEmail::new_from_raw_email(LEGACY_EMAIL).await.unwrap();
+        let account_code: AccountCode =
+            serde_json::from_value(Value::String(format!("0x{}", ACCOUNT_CODE))).unwrap();
+
+        let circuit_input =
+            generate_legacy_circuit_input(LEGACY_EMAIL, &parsed_email, &account_code, 1024)
+                .await
+                .unwrap();
+        let circuit_input: Value = serde_json::from_str(&circuit_input).unwrap();
+
+        let header = &parsed_email.canonicalized_header;
+        let subject_idx = circuit_input["subject_idx"].as_u64().unwrap() as usize;
+        assert!(header[subject_idx..].starts_with("Send 0.12 ETH to alice@gmail.com"));
+        let code_idx = circuit_input["code_idx"].as_u64().unwrap() as usize;
+        assert_eq!(
+            &header[code_idx..code_idx + ACCOUNT_CODE.len()],
+            ACCOUNT_CODE
+        );
+
+        for field in [
+            "body_hash_idx",
+            "precomputed_sha",
+            "padded_body",
+            "padded_body_len",
+            "command_idx",
+            "padded_cleaned_body",
+        ] {
+            assert!(circuit_input.get(field).is_none(), "{} was kept", field);
+        }
+    }
 }
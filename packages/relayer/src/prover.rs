@@ -0,0 +1,320 @@
+//! Backends that generate the proofs of replies.
+//!
+//! The backend is selected with `prover` in the config:
+//! - `remote` requests proofs from the prover services of the `proverPool` (the default).
+//! - `local` generates the witness and the proof on this machine with rapidsnark or snarkjs, like
+//!   `packages/prover/circom_proofgen.sh`, for deployments that prove on their own hardware.
+//!
+//! A circuit profile with its own `proverUrl` is always proven by that prover service, with the
+//! timeout of the pool.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi::{encode, Token},
+    types::{Bytes, U256},
+};
+use relayer_utils::{generate_proof, LOG};
+use serde::Deserialize;
+use slog::info;
+use tokio::process::Command;
+use uuid::Uuid;
+
+use crate::{
+    config::{
+        CircuitProfileConfig, LocalProverConfig, ProofBackend, ProverConfig, WitnessGenerator,
+    },
+    RelayerState,
+};
+
+/// A backend that generates Groth16 proofs.
+#[async_trait]
+pub trait Prover: Send + Sync + std::fmt::Debug {
+    /// Generates a proof for a circuit.
+    ///
+    /// # Arguments
+    ///
+    /// * `circuit_input` - The circuit input as JSON.
+    /// * `circuit` - The name of the circuit.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the ABI-encoded proof and the public signals.
+    async fn prove(&self, circuit_input: &str, circuit: &str) -> Result<(Bytes, Vec<U256>)>;
+}
+
+/// Creates the prover for a circuit profile.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `profile` - The circuit profile the proof is generated for.
+///
+/// # Returns
+///
+/// The prover backend.
+pub fn create_prover(
+    relayer_state: &RelayerState,
+    profile: &CircuitProfileConfig,
+) -> Arc<dyn Prover> {
+    let config = &relayer_state.config;
+    if let Some(url) = &profile.prover_url {
+        return Arc::new(RemoteProver {
+            url: url.clone(),
+            timeout: Duration::from_secs(config.prover_pool.timeout_secs.max(1)),
+        });
+    }
+
+    match &config.prover {
+        ProverConfig::Remote => relayer_state.prover_pool.clone(),
+        ProverConfig::Local(local_config) => Arc::new(LocalProver {
+            config: local_config.clone(),
+        }),
+    }
+}
+
+/// Requests proofs from a single prover service.
+#[derive(Debug)]
+pub struct RemoteProver {
+    url: String,
+    timeout: Duration,
+}
+
+#[async_trait]
+impl Prover for RemoteProver {
+    async fn prove(&self, circuit_input: &str, circuit: &str) -> Result<(Bytes, Vec<U256>)> {
+        tokio::time::timeout(
+            self.timeout,
+            generate_proof(circuit_input, circuit, &self.url),
+        )
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "Prover {} did not return a proof within {} seconds",
+                self.url,
+                self.timeout.as_secs()
+            )
+        })?
+    }
+}
+
+/// Generates proofs on this machine.
+#[derive(Debug)]
+pub struct LocalProver {
+    config: LocalProverConfig,
+}
+
+/// A Groth16 proof as written by rapidsnark and snarkjs.
+#[derive(Debug, Deserialize)]
+struct ProofJson {
+    pi_a: Vec<String>,
+    pi_b: Vec<Vec<String>>,
+    pi_c: Vec<String>,
+}
+
+#[async_trait]
+impl Prover for LocalProver {
+    async fn prove(&self, circuit_input: &str, circuit: &str) -> Result<(Bytes, Vec<U256>)> {
+        let build_dir = PathBuf::from(&self.config.build_dir);
+        tokio::fs::create_dir_all(&build_dir).await?;
+
+        let nonce = Uuid::new_v4();
+        let files = [
+            build_dir.join(format!("input_{}_{}.json", circuit, nonce)),
+            build_dir.join(format!("witness_{}_{}.wtns", circuit, nonce)),
+            build_dir.join(format!("proof_{}_{}.json", circuit, nonce)),
+            build_dir.join(format!("public_{}_{}.json", circuit, nonce)),
+        ];
+
+        let result = self.prove_with_files(circuit_input, circuit, &files).await;
+
+        // The files are only needed while proving, so they are removed whether or not it worked
+        for file in &files {
+            let _ = tokio::fs::remove_file(file).await;
+        }
+        result
+    }
+}
+
+impl LocalProver {
+    /// Writes the input, generates the witness and the proof, and reads the proof back.
+    async fn prove_with_files(
+        &self,
+        circuit_input: &str,
+        circuit: &str,
+        [input_path, witness_path, proof_path, public_path]: &[PathBuf; 4],
+    ) -> Result<(Bytes, Vec<U256>)> {
+        let params_dir = PathBuf::from(&self.config.params_dir);
+        tokio::fs::write(input_path, circuit_input).await?;
+
+        info!(LOG, "Generating witness for {}", circuit);
+        let mut witness_command = match self.config.witness_generator {
+            WitnessGenerator::Cpp => {
+                Command::new(params_dir.join(format!("{}_cpp", circuit)).join(circuit))
+            }
+            WitnessGenerator::Wasm => {
+                let mut command = Command::new(&self.config.snarkjs_path);
+                command
+                    .arg("wc")
+                    .arg(params_dir.join(format!("{}.wasm", circuit)));
+                command
+            }
+        };
+        witness_command.arg(input_path).arg(witness_path);
+        run(witness_command).await?;
+
+        info!(LOG, "Generating proof for {}", circuit);
+        let zkey_path = params_dir.join(format!("{}.zkey", circuit));
+        let mut prover_command = match self.config.backend {
+            ProofBackend::Rapidsnark => Command::new(&self.config.rapidsnark_path),
+            ProofBackend::Snarkjs => {
+                let mut command = Command::new(&self.config.snarkjs_path);
+                command.args(["groth16", "prove"]);
+                command
+            }
+        };
+        prover_command
+            .arg(zkey_path)
+            .arg(witness_path)
+            .arg(proof_path)
+            .arg(public_path);
+        run(prover_command).await?;
+
+        let proof: ProofJson = read_json(proof_path).await?;
+        let public_signals: Vec<String> = read_json(public_path).await?;
+        let public_signals = public_signals
+            .iter()
+            .map(|signal| U256::from_dec_str(signal))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((encode_proof(&proof)?, public_signals))
+    }
+}
+
+/// Runs a command to completion, failing with its output if it exits unsuccessfully.
+async fn run(mut command: Command) -> Result<()> {
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+    let output = command
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", program))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} exited with {}: {}",
+            program,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// Reads a JSON file written by the prover.
+async fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let content = tokio::fs::read(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(serde_json::from_slice(&content)?)
+}
+
+/// ABI-encodes a proof as `(uint256[2], uint256[2][2], uint256[2])` for the verifier contract.
+///
+/// The verifier expects the coordinates of the points of `pi_b` in reverse order.
+fn encode_proof(proof: &ProofJson) -> Result<Bytes> {
+    let point = |coordinates: &[String], reversed: bool| -> Result<Token> {
+        // Points are written in projective coordinates, the trailing z coordinate is dropped
+        let [x, y, ..] = coordinates else {
+            return Err(anyhow!("Invalid proof point"));
+        };
+        let (first, second) = if reversed { (y, x) } else { (x, y) };
+        Ok(Token::FixedArray(vec![
+            Token::Uint(U256::from_dec_str(first)?),
+            Token::Uint(U256::from_dec_str(second)?),
+        ]))
+    };
+
+    let pi_b = proof
+        .pi_b
+        .get(..2)
+        .ok_or_else(|| anyhow!("Invalid proof point"))?
+        .iter()
+        .map(|coordinates| point(coordinates, true))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(encode(&[
+        point(&proof.pi_a, false)?,
+        Token::FixedArray(pi_b),
+        point(&proof.pi_c, false)?,
+    ])
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::utils::hex;
+
+    use super::*;
+
+    /// A snarkjs `proof.json` made of the generators of BN254, so its points are on the curve.
+    const PROOF_JSON: &str = r#"{
+        "pi_a": ["1", "2", "1"],
+        "pi_b": [
+            [
+                "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+                "11559732032986387107991004021392285783925812861821192530917403151452391805634"
+            ],
+            [
+                "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+                "4082367875863433681332203403145435568316851327593401208105741076214120093531"
+            ],
+            ["1", "0"]
+        ],
+        "pi_c": [
+            "1",
+            "21888242871839275222246405745257275088696311157297823662689037894645226208581",
+            "1"
+        ],
+        "protocol": "groth16",
+        "curve": "bn128"
+    }"#;
+
+    /// The proof arguments `snarkjs zkey export soliditycalldata` prints for `PROOF_JSON`.
+    const CALLDATA: [&str; 8] = [
+        "0x0000000000000000000000000000000000000000000000000000000000000001",
+        "0x0000000000000000000000000000000000000000000000000000000000000002",
+        "0x198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+        "0x1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+        "0x090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+        "0x12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+        "0x0000000000000000000000000000000000000000000000000000000000000001",
+        "0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd45",
+    ];
+
+    #[test]
+    fn encode_proof_matches_verifier_calldata() {
+        let proof: ProofJson = serde_json::from_str(PROOF_JSON).unwrap();
+        let expected: String = CALLDATA
+            .iter()
+            .map(|word| word.trim_start_matches("0x"))
+            .collect();
+        assert_eq!(hex::encode(encode_proof(&proof).unwrap()), expected);
+    }
+
+    #[test]
+    fn encode_proof_rejects_truncated_points() {
+        let mut proof: ProofJson = serde_json::from_str(PROOF_JSON).unwrap();
+        proof.pi_b.truncate(1);
+        assert!(encode_proof(&proof).is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! A pool of remote prover services.
+//!
+//! Proofs are requested from the prover services in `proverPool.endpoints`, picked at random by
+//! their weights, where a weight of 0 disables a prover service. A prover service that fails or
+//! does not return a proof within the timeout is replaced by another one for the next attempt.
+//! Prover services failing repeatedly, or failing their periodic health probe, are only used when
+//! no other one is available. A failing prover service is tried again once the failure cooldown
+//! has passed, and counts as available again when it returns a proof.
+//!
+//! Latency and failure counts are kept in memory per relayer instance and listed on the admin API.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ethers::{
+    core::rand::{thread_rng, Rng},
+    types::{Bytes, U256},
+};
+use relayer_utils::{generate_proof, LOG};
+use reqwest::Client;
+use serde::Serialize;
+use slog::{info, warn};
+
+use crate::{
+    config::{Config, ProverPoolConfig},
+    prover::Prover,
+    RelayerState,
+};
+
+/// Remote prover services proofs are requested from with failover.
+#[derive(Debug)]
+pub struct ProverPool {
+    endpoints: Vec<ProverEndpoint>,
+    config: ProverPoolConfig,
+    http_client: Client,
+}
+
+/// A prover service in the pool.
+#[derive(Debug)]
+struct ProverEndpoint {
+    url: String,
+    weight: u32,
+    stats: Mutex<ProverStats>,
+}
+
+/// The statistics of a prover service since the relayer started.
+#[derive(Debug, Clone)]
+struct ProverStats {
+    requests: u64,
+    successes: u64,
+    failures: u64,
+    timeouts: u64,
+    consecutive_failures: u32,
+    last_failure: Option<Instant>,
+    total_latency_ms: u64,
+    last_latency_ms: Option<u64>,
+    last_error: Option<String>,
+    healthy: bool,
+    last_health_check: Option<DateTime<Utc>>,
+}
+
+/// The state of a prover service as listed on the admin API.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProverStatus {
+    pub url: String,
+    pub weight: u32,
+    /// Whether proofs are requested from the prover service ahead of failing ones.
+    pub available: bool,
+    pub healthy: bool,
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub timeouts: u64,
+    pub consecutive_failures: u32,
+    /// The average time in milliseconds of the successful proofs.
+    pub average_latency_ms: Option<u64>,
+    pub last_latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_health_check: Option<DateTime<Utc>>,
+}
+
+impl ProverPool {
+    /// Creates the pool from the config, falling back to `proverUrl` if no endpoint is listed.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration of the relayer.
+    /// * `http_client` - The HTTP client used for health probes.
+    ///
+    /// # Returns
+    ///
+    /// The `ProverPool`.
+    pub fn new(config: &Config, http_client: Client) -> Self {
+        let pool_config = config.prover_pool.clone();
+        let endpoints = if pool_config.endpoints.is_empty() {
+            vec![(config.prover_url.clone(), 1)]
+        } else {
+            pool_config
+                .endpoints
+                .iter()
+                .map(|endpoint| (endpoint.url.clone(), endpoint.weight))
+                .collect()
+        };
+
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(url, weight)| ProverEndpoint {
+                    url,
+                    weight,
+                    stats: Mutex::new(ProverStats::new()),
+                })
+                .collect(),
+            config: pool_config,
+            http_client,
+        }
+    }
+
+    /// Lists the state of every prover service in the pool.
+    ///
+    /// # Returns
+    ///
+    /// A vector with the `ProverStatus` of every prover service.
+    pub fn status(&self) -> Vec<ProverStatus> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| {
+                let stats = endpoint.stats();
+                ProverStatus {
+                    url: endpoint.url.clone(),
+                    weight: endpoint.weight,
+                    available: endpoint.is_enabled() && stats.is_available(&self.config),
+                    healthy: stats.healthy,
+                    requests: stats.requests,
+                    successes: stats.successes,
+                    failures: stats.failures,
+                    timeouts: stats.timeouts,
+                    consecutive_failures: stats.consecutive_failures,
+                    average_latency_ms: (stats.successes > 0)
+                        .then(|| stats.total_latency_ms / stats.successes),
+                    last_latency_ms: stats.last_latency_ms,
+                    last_error: stats.last_error,
+                    last_health_check: stats.last_health_check,
+                }
+            })
+            .collect()
+    }
+
+    /// Probes every enabled prover service and records whether it is healthy.
+    ///
+    /// A prover service counts as healthy if its health route answers with a success status. The
+    /// probe does not clear the failures of its proofs.
+    pub async fn check_health(&self) {
+        let timeout = Duration::from_secs(self.config.health_check_timeout_secs.max(1));
+        for endpoint in self
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.is_enabled())
+        {
+            let health_url = format!(
+                "{}/{}",
+                endpoint.url.trim_end_matches('/'),
+                self.config.health_check_path.trim_start_matches('/')
+            );
+            let healthy = match self
+                .http_client
+                .get(&health_url)
+                .timeout(timeout)
+                .send()
+                .await
+            {
+                Ok(response) => response.status().is_success(),
+                Err(_) => false,
+            };
+
+            let mut stats = endpoint.stats.lock().unwrap();
+            if healthy != stats.healthy {
+                info!(
+                    LOG,
+                    "Prover {} is {}",
+                    endpoint.url,
+                    if healthy { "healthy" } else { "unhealthy" }
+                );
+            }
+            stats.healthy = healthy;
+            stats.last_health_check = Some(Utc::now());
+        }
+    }
+
+    /// Picks a prover service by weight among the enabled ones not tried yet.
+    ///
+    /// Available prover services are preferred, the others are only picked if none is left.
+    fn select(&self, tried: &[usize]) -> Option<usize> {
+        let untried: Vec<usize> = (0..self.endpoints.len())
+            .filter(|idx| !tried.contains(idx) && self.endpoints[*idx].is_enabled())
+            .collect();
+        let available: Vec<usize> = untried
+            .iter()
+            .copied()
+            .filter(|&idx| self.endpoints[idx].stats().is_available(&self.config))
+            .collect();
+        let candidates = if available.is_empty() {
+            untried
+        } else {
+            available
+        };
+
+        let total_weight: u64 = candidates
+            .iter()
+            .map(|&idx| self.endpoints[idx].weight as u64)
+            .sum();
+        if total_weight == 0 {
+            return None;
+        }
+
+        let mut pick = thread_rng().gen_range(0..total_weight);
+        for idx in candidates {
+            let weight = self.endpoints[idx].weight as u64;
+            if pick < weight {
+                return Some(idx);
+            }
+            pick -= weight;
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl Prover for ProverPool {
+    async fn prove(&self, circuit_input: &str, circuit: &str) -> Result<(Bytes, Vec<U256>)> {
+        let timeout = Duration::from_secs(self.config.timeout_secs.max(1));
+        let mut tried = Vec::new();
+        let mut last_error = None;
+
+        while tried.len() < self.config.max_attempts.max(1) {
+            let Some(idx) = self.select(&tried) else {
+                break;
+            };
+            tried.push(idx);
+            let endpoint = &self.endpoints[idx];
+
+            let started = Instant::now();
+            let result = tokio::time::timeout(
+                timeout,
+                generate_proof(circuit_input, circuit, &endpoint.url),
+            )
+            .await;
+            let latency_ms = started.elapsed().as_millis() as u64;
+
+            let error = match result {
+                Ok(Ok(proof)) => {
+                    endpoint.record_success(latency_ms);
+                    return Ok(proof);
+                }
+                Ok(Err(e)) => {
+                    endpoint.record_failure(&e.to_string(), false);
+                    e
+                }
+                Err(_) => {
+                    endpoint.record_failure("Timed out", true);
+                    anyhow!(
+                        "Prover {} did not return a proof within {} seconds",
+                        endpoint.url,
+                        timeout.as_secs()
+                    )
+                }
+            };
+            warn!(
+                LOG,
+                "Prover {} failed to prove {}: {:?}", endpoint.url, circuit, error
+            );
+            last_error = Some(error);
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No prover is configured")))
+    }
+}
+
+impl ProverEndpoint {
+    /// Checks whether proofs may be requested from the prover service, i.e. its weight is not 0.
+    fn is_enabled(&self) -> bool {
+        self.weight > 0
+    }
+
+    /// Returns a copy of the statistics of the prover service.
+    fn stats(&self) -> ProverStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Records a proof returned by the prover service.
+    fn record_success(&self, latency_ms: u64) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        stats.successes += 1;
+        stats.consecutive_failures = 0;
+        stats.total_latency_ms += latency_ms;
+        stats.last_latency_ms = Some(latency_ms);
+    }
+
+    /// Records a failed or timed out proof request.
+    fn record_failure(&self, error: &str, timed_out: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.requests += 1;
+        stats.failures += 1;
+        if timed_out {
+            stats.timeouts += 1;
+        }
+        stats.consecutive_failures += 1;
+        stats.last_failure = Some(Instant::now());
+        stats.last_error = Some(error.to_string());
+    }
+}
+
+impl ProverStats {
+    /// Creates the statistics of a prover service, which counts as healthy until probed.
+    fn new() -> Self {
+        Self {
+            requests: 0,
+            successes: 0,
+            failures: 0,
+            timeouts: 0,
+            consecutive_failures: 0,
+            last_failure: None,
+            total_latency_ms: 0,
+            last_latency_ms: None,
+            last_error: None,
+            healthy: true,
+            last_health_check: None,
+        }
+    }
+
+    /// Checks whether the prover service is healthy and has not failed too often in a row, or
+    /// its last failure is older than the failure cooldown.
+    fn is_available(&self, config: &ProverPoolConfig) -> bool {
+        let cooldown = Duration::from_secs(config.failure_cooldown_secs);
+        let cooled_down = !self
+            .last_failure
+            .is_some_and(|last_failure| last_failure.elapsed() < cooldown);
+        self.healthy
+            && (self.consecutive_failures < config.max_consecutive_failures.max(1) || cooled_down)
+    }
+}
+
+/// Probes the prover services periodically until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_prover_health_checks(relayer_state: RelayerState) {
+    let prover_pool: Arc<ProverPool> = relayer_state.prover_pool.clone();
+    let check_interval = Duration::from_secs(
+        relayer_state
+            .config
+            .prover_pool
+            .health_check_interval_secs
+            .max(1),
+    );
+
+    info!(LOG, "Prover health checks started");
+    loop {
+        prover_pool.check_health().await;
+        tokio::time::sleep(check_interval).await;
+    }
+}
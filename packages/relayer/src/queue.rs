@@ -0,0 +1,353 @@
+//! Durable background job queue for proving, on-chain and webhook work.
+//!
+//! Jobs are stored in the `jobs` table and claimed by worker tasks with `FOR UPDATE SKIP LOCKED`,
+//! so several workers (and several relayer replicas) can share the same queue. The lease of a job
+//! is renewed while it runs, so a job is only claimed again once its worker crashed.
+
+use std::{panic::AssertUnwindSafe, time::Duration};
+
+use anyhow::{anyhow, Result};
+use ethers::abi::{AbiDecode, AbiEncode};
+use ethers::types::Bytes;
+use futures::FutureExt;
+use relayer_utils::{ParsedEmail, LOG};
+use serde::{Deserialize, Serialize};
+use slog::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    abis::EmailAuthMsg,
+    config::Config,
+    mail::{complete_request, handle_email, handle_email_event, submit_email_auth_msg, EmailEvent},
+    model::{
+        claim_job, complete_job, fail_job, fail_request, get_request, insert_job, renew_job_lease,
+        ErrorCode, JobModel, JobStatus, RequestStatus,
+    },
+    webhook::{deliver_webhook, notify_status},
+    RelayerState,
+};
+
+/// Represents a unit of background work for a request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Job {
+    /// Verifies DKIM, generates the proof and saves the email auth message for a reply.
+    #[serde(rename_all = "camelCase")]
+    ProcessReply { email: String },
+    /// Submits a saved email auth message on-chain.
+    #[serde(rename_all = "camelCase")]
+    SubmitOnChain {
+        email: String,
+        /// The ABI-encoded `EmailAuthMsg`.
+        email_auth_msg: Bytes,
+    },
+    /// Posts a status transition of a request to its callback URL.
+    #[serde(rename_all = "camelCase")]
+    DeliverWebhook { url: String, status: RequestStatus },
+}
+
+impl Job {
+    /// Returns the name of the job kind, as stored in the `kind` column.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::ProcessReply { .. } => "processReply",
+            Job::SubmitOnChain { .. } => "submitOnChain",
+            Job::DeliverWebhook { .. } => "deliverWebhook",
+        }
+    }
+
+    /// Returns the number of attempts after which the job is dead-lettered.
+    fn max_attempts(&self, config: &Config) -> i32 {
+        match self {
+            Job::DeliverWebhook { .. } => config.webhook.max_attempts,
+            _ => config.queue.max_attempts,
+        }
+    }
+}
+
+/// Adds a job for a request to the queue.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `request_id` - The unique identifier of the request.
+/// * `job` - The job to enqueue.
+///
+/// # Returns
+///
+/// A `Result` containing the `Uuid` of the new job.
+pub async fn enqueue(relayer_state: &RelayerState, request_id: Uuid, job: Job) -> Result<Uuid> {
+    let job_id = insert_job(
+        &relayer_state.db,
+        request_id,
+        &job,
+        job.max_attempts(&relayer_state.config),
+    )
+    .await?;
+    info!(
+        LOG,
+        "Job {} ({}) enqueued for request {}",
+        job_id,
+        job.kind(),
+        request_id
+    );
+    Ok(job_id)
+}
+
+/// Runs a worker that claims and processes jobs until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `worker_id` - The index of the worker, used for logging.
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_worker(worker_id: usize, relayer_state: RelayerState) {
+    let queue_config = relayer_state.config.queue.clone();
+    let poll_interval = Duration::from_millis(queue_config.poll_interval_ms);
+
+    info!(LOG, "Job worker {} started", worker_id);
+    loop {
+        match claim_job(&relayer_state.db, queue_config.lease_secs).await {
+            Ok(Some(job)) => {
+                info!(
+                    LOG,
+                    "Worker {} claimed job {} (attempt {}/{})",
+                    worker_id,
+                    job.id,
+                    job.attempts,
+                    job.max_attempts
+                );
+                process_job(job, &relayer_state).await;
+            }
+            Ok(None) => tokio::time::sleep(poll_interval).await,
+            Err(e) => {
+                error!(LOG, "Worker {} failed to claim a job: {:?}", worker_id, e);
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+}
+
+/// Runs a job while renewing its lease, turning a panic of the job into an error.
+///
+/// The lease is renewed every third of `queue.leaseSecs`, so a long-running job is not claimed by
+/// another worker, and a panicking job does not take its worker down.
+async fn run_with_lease(job: &JobModel, relayer_state: &RelayerState) -> Result<Option<Job>> {
+    let renew_interval = Duration::from_secs((relayer_state.config.queue.lease_secs / 3).max(1));
+    let mut heartbeat = tokio::time::interval(renew_interval);
+    // The first tick completes immediately, the lease was just taken by the claim
+    heartbeat.tick().await;
+
+    let run = AssertUnwindSafe(run_job(job, relayer_state)).catch_unwind();
+    tokio::pin!(run);
+    loop {
+        tokio::select! {
+            result = &mut run => {
+                return result.unwrap_or_else(|panic| {
+                    let message = panic
+                        .downcast_ref::<&str>()
+                        .map(|message| message.to_string())
+                        .or_else(|| panic.downcast_ref::<String>().cloned())
+                        .unwrap_or_default();
+                    Err(anyhow!("Job panicked: {}", message))
+                });
+            }
+            _ = heartbeat.tick() => {
+                if let Err(e) = renew_job_lease(&relayer_state.db, job.id).await {
+                    warn!(LOG, "Failed to renew the lease of job {}: {:?}", job.id, e);
+                }
+            }
+        }
+    }
+}
+
+/// Runs a claimed job and records its outcome.
+///
+/// A follow-up job is enqueued in the same transaction that completes the job, so it is enqueued
+/// only once even if the job ran again after its lease expired. Failed jobs are retried with
+/// exponential backoff until they run out of attempts, at which point they are dead-lettered and
+/// the sender of the reply is notified.
+async fn process_job(job: JobModel, relayer_state: &RelayerState) {
+    let result = run_with_lease(&job, relayer_state).await;
+
+    let outcome = match result {
+        Ok(None) => complete_job(&relayer_state.db, job.id, None)
+            .await
+            .map(|_| ()),
+        Ok(Some(next)) => {
+            let max_attempts = next.max_attempts(&relayer_state.config);
+            complete_job(&relayer_state.db, job.id, Some((&next, max_attempts)))
+                .await
+                .map(|follow_up_id| {
+                    if let Some(follow_up_id) = follow_up_id {
+                        info!(
+                            LOG,
+                            "Job {} ({}) enqueued for request {}",
+                            follow_up_id,
+                            next.kind(),
+                            job.request_id
+                        );
+                    }
+                })
+        }
+        Err(e) => {
+            error!(LOG, "Job {} failed: {:?}", job.id, e);
+            let backoff = backoff_secs(job.attempts, relayer_state);
+            match fail_job(&relayer_state.db, &job, &format!("{:#}", e), backoff).await {
+                Ok(JobStatus::Dead) => {
+                    handle_dead_job(&job, e, relayer_state).await;
+                    Ok(())
+                }
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            }
+        }
+    };
+
+    if let Err(e) = outcome {
+        error!(LOG, "Failed to record outcome of job {}: {:?}", job.id, e);
+    }
+}
+
+/// Executes the work of a single job.
+///
+/// # Returns
+///
+/// A `Result` containing the job to run next for the same request, if any.
+async fn run_job(job: &JobModel, relayer_state: &RelayerState) -> Result<Option<Job>> {
+    let request = get_request(&relayer_state.db, job.request_id).await?;
+
+    // A request that already ended is not processed any further
+    if !matches!(job.payload.0, Job::DeliverWebhook { .. }) && request.status.is_terminal() {
+        info!(
+            LOG,
+            "Skipping job {} of request {} in state {:?}", job.id, request.id, request.status
+        );
+        return Ok(None);
+    }
+
+    match &job.payload.0 {
+        Job::ProcessReply { email } => {
+            let email_auth_msg =
+                handle_email(email, request.clone(), relayer_state.clone()).await?;
+
+            // Hand over to a separate job so a failing transaction does not redo the proof
+            if request.email_tx_auth.on_chain.is_some() {
+                return Ok(Some(Job::SubmitOnChain {
+                    email: email.clone(),
+                    email_auth_msg: email_auth_msg.encode().into(),
+                }));
+            }
+
+            finish(email, request.id, relayer_state).await?;
+            Ok(None)
+        }
+        Job::SubmitOnChain {
+            email,
+            email_auth_msg,
+        } => {
+            let on_chain = request
+                .email_tx_auth
+                .on_chain
+                .clone()
+                .ok_or_else(|| anyhow!("Request has no on-chain submission"))?;
+            let email_auth_msg = EmailAuthMsg::decode(email_auth_msg)?;
+            submit_email_auth_msg(&request, &on_chain, email_auth_msg, relayer_state).await?;
+
+            finish(email, request.id, relayer_state).await?;
+            Ok(None)
+        }
+        Job::DeliverWebhook { url, status } => {
+            deliver_webhook(job, url, *status, relayer_state).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Completes the request and sends the completion email.
+async fn finish(email: &str, request_id: Uuid, relayer_state: &RelayerState) -> Result<()> {
+    let Some(event) = complete_request(email, request_id, relayer_state).await? else {
+        info!(LOG, "Request {} has already ended", request_id);
+        return Ok(());
+    };
+
+    // The request is complete at this point, so a failed email must not retry the job
+    if let Err(e) = handle_email_event(event, relayer_state.clone()).await {
+        error!(LOG, "Error handling email event: {:?}", e);
+    }
+    Ok(())
+}
+
+/// Moves the request of a dead-lettered job to its failure state and notifies the sender.
+///
+/// A request that already ended keeps its status. A callback that could not be delivered does not affect the request.
+async fn handle_dead_job(job: &JobModel, err: anyhow::Error, relayer_state: &RelayerState) {
+    let (status, email) = match &job.payload.0 {
+        // A reply that cannot be parsed or does not match the command template is rejected
+        Job::ProcessReply { email } => match ErrorCode::of(&err) {
+            ErrorCode::InvalidEmail | ErrorCode::InvalidCommand => (RequestStatus::Rejected, email),
+            _ => (RequestStatus::Failed, email),
+        },
+        Job::SubmitOnChain { email, .. } => (RequestStatus::TransactionFailed, email),
+        Job::DeliverWebhook { url, .. } => {
+            error!(
+                LOG,
+                "Giving up on webhook for request {} to {}", job.request_id, url
+            );
+            return;
+        }
+    };
+    match fail_request(
+        &relayer_state.db,
+        job.request_id,
+        status,
+        ErrorCode::of(&err),
+        &format!("{:#}", err),
+    )
+    .await
+    {
+        Ok(true) => notify_status(relayer_state, job.request_id, status).await,
+        Ok(false) => info!(LOG, "Request {} has already ended", job.request_id),
+        Err(e) => error!(LOG, "Failed to update request {}: {:?}", job.request_id, e),
+    }
+
+    let parsed_email = match ParsedEmail::new_from_raw_email(email).await {
+        Ok(parsed_email) => parsed_email,
+        Err(e) => {
+            error!(LOG, "Failed to parse email of job {}: {:?}", job.id, e);
+            return;
+        }
+    };
+    let email_addr = match parsed_email.get_from_addr() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!(LOG, "Failed to get sender of job {}: {:?}", job.id, e);
+            return;
+        }
+    };
+
+    if let Err(e) = handle_email_event(
+        EmailEvent::Error {
+            email_addr,
+            error: format!("{:#}", err),
+            original_subject: parsed_email
+                .get_subject_all()
+                .unwrap_or("Unknown Error".to_string()),
+            original_message_id: parsed_email.get_message_id().ok(),
+        },
+        relayer_state.clone(),
+    )
+    .await
+    {
+        error!(LOG, "Error handling email event: {:?}", e);
+    }
+}
+
+/// Computes the delay before the next attempt of a job, doubling with every attempt.
+fn backoff_secs(attempts: i32, relayer_state: &RelayerState) -> u64 {
+    let queue_config = &relayer_state.config.queue;
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    queue_config
+        .backoff_base_secs
+        .saturating_mul(2u64.pow(exponent))
+        .min(queue_config.backoff_max_secs)
+}
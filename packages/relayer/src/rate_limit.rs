@@ -0,0 +1,155 @@
+//! Rate limiting of submitted requests.
+//!
+//! Requests are counted in fixed windows per API client, per recipient email address and per
+//! recipient domain. The counters live in the `rate_limits` table, so they are shared between
+//! relayer replicas and survive restarts.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::{
+    http::{header::RETRY_AFTER, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use relayer_utils::LOG;
+use serde_json::json;
+use slog::{error, info, warn};
+
+use crate::{
+    mail::normalize_email_address,
+    model::{hit_rate_limits, prune_rate_limits, ApiClientModel},
+    RelayerState,
+};
+
+/// Represents a request rejected because one of its rate limits is exhausted.
+#[derive(Debug)]
+pub struct RateLimitExceeded {
+    /// The scope of the exhausted limit: `client`, `recipient` or `domain`.
+    pub scope: &'static str,
+    /// The number of seconds until the current window ends.
+    pub retry_after_secs: u64,
+}
+
+impl IntoResponse for RateLimitExceeded {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, self.retry_after_secs.to_string())],
+            Json(json!({
+                "error": format!("Rate limit exceeded for {}", self.scope),
+                "retryAfter": self.retry_after_secs,
+            })),
+        )
+            .into_response()
+    }
+}
+
+/// Counts submitted requests against the rate limits of their client and recipients.
+///
+/// All limits are checked before any of them is counted, so a rejected submission does not use up
+/// the limits it did not exceed.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `api_client` - The authenticated API client, if authentication is enabled.
+/// * `email_addresses` - The recipients of the command emails, one per request.
+///
+/// # Returns
+///
+/// A `Result` containing the exhausted limit, or `None` if the requests may proceed.
+pub async fn check_submit_rate_limits(
+    relayer_state: &RelayerState,
+    api_client: Option<&ApiClientModel>,
+    email_addresses: &[&str],
+) -> Result<Option<RateLimitExceeded>> {
+    let config = &relayer_state.config.rate_limit;
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let window_secs = config.window_secs.max(1) as i64;
+    let now = Utc::now().timestamp();
+    let window_start = window_start(window_secs, now)?;
+
+    let mut scopes = vec![];
+    let mut hits: Vec<(String, i32, i32)> = vec![];
+    let mut hit = |scope: &'static str, key: String, limit: u32| match hits
+        .iter()
+        .position(|(hit_key, _, _)| *hit_key == key)
+    {
+        Some(idx) => hits[idx].1 += 1,
+        None => {
+            scopes.push(scope);
+            hits.push((key, 1, limit as i32));
+        }
+    };
+    for email_address in email_addresses {
+        let recipient = normalize_email_address(email_address);
+        let domain = recipient
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_string())
+            .unwrap_or_default();
+
+        if let (Some(limit), Some(api_client)) = (config.per_client, api_client) {
+            hit("client", format!("client:{}", api_client.id), limit);
+        }
+        if let Some(limit) = config.per_recipient {
+            hit("recipient", format!("recipient:{}", recipient), limit);
+        }
+        if let Some(limit) = config.per_domain {
+            hit("domain", format!("domain:{}", domain), limit);
+        }
+    }
+
+    let Some((idx, count)) = hit_rate_limits(&relayer_state.db, &hits, window_start).await? else {
+        return Ok(None);
+    };
+
+    let (key, _, limit) = &hits[idx];
+    let retry_after_secs = (window_start.timestamp() + window_secs - now).max(1) as u64;
+    warn!(
+        LOG,
+        "Rate limit exceeded for {} ({}/{} in window), retry after {}s",
+        key,
+        count,
+        limit,
+        retry_after_secs
+    );
+    Ok(Some(RateLimitExceeded {
+        scope: scopes[idx],
+        retry_after_secs,
+    }))
+}
+
+/// Deletes the counters of ended windows periodically until the relayer shuts down.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+pub async fn run_rate_limit_pruning(relayer_state: RelayerState) {
+    let window_secs = relayer_state.config.rate_limit.window_secs.max(1) as i64;
+
+    info!(LOG, "Rate limit pruning started");
+    loop {
+        // Old windows are never read again
+        match window_start(window_secs, Utc::now().timestamp()) {
+            Ok(window_start) => {
+                if let Err(e) = prune_rate_limits(&relayer_state.db, window_start).await {
+                    error!(LOG, "Failed to prune rate limits: {:?}", e);
+                }
+            }
+            Err(e) => error!(LOG, "Failed to prune rate limits: {:?}", e),
+        }
+        tokio::time::sleep(Duration::from_secs(window_secs as u64)).await;
+    }
+}
+
+/// Returns the start of the fixed window containing a timestamp.
+fn window_start(window_secs: i64, now: i64) -> Result<DateTime<Utc>> {
+    Utc.timestamp_opt(now - now.rem_euclid(window_secs), 0)
+        .single()
+        .ok_or_else(|| anyhow::anyhow!("Invalid rate limit window"))
+}
@@ -1,14 +1,21 @@
 use std::sync::Arc;
 
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{delete, get, post},
     Router,
 };
 
 use crate::{
+    auth::{
+        require_admin_token, require_api_key, require_api_key_or_stream_token, require_signature,
+    },
     handler::{
-        account_salt_handler, get_status_handler, health_checker_handler, receive_email_handler,
-        submit_handler,
+        account_salt_handler, create_api_key_handler, create_stream_token_handler,
+        get_api_keys_handler, get_group_status_handler, get_provers_handler,
+        get_revoked_dkim_keys_handler, get_status_handler, get_status_stream_handler,
+        health_checker_handler, receive_email_handler, revoke_api_key_handler,
+        submit_batch_handler, submit_handler,
     },
     RelayerState,
 };
@@ -16,6 +23,8 @@ use crate::{
 /// Creates and configures the router for the relayer service.
 ///
 /// This function sets up the routes for the API endpoints and associates them with their respective handlers.
+/// Client endpoints require an API key (the status stream also accepts a stream token), forwarded
+/// emails require a signature from the IMAP service, and admin endpoints require the admin token.
 /// It also attaches the shared state to the router.
 ///
 /// # Arguments
@@ -26,17 +35,74 @@ use crate::{
 ///
 /// A `Router` configured with the necessary routes and state.
 pub fn create_router(relayer_state: Arc<RelayerState>) -> Router {
+    // Routes for API clients
+    let client_routes = Router::new()
+        // Route for submitting email transaction authentication requests
+        .route("/api/submit", post(submit_handler))
+        // Route for submitting a group of requests at once
+        .route("/api/submitBatch", post(submit_batch_handler))
+        // Route for retrieving the status of a specific request
+        .route("/api/status/:id", get(get_status_handler))
+        // Route for issuing a token for the status stream of a specific request
+        .route(
+            "/api/status/:id/streamToken",
+            post(create_stream_token_handler),
+        )
+        // Route for retrieving the status of a group of requests
+        .route("/api/groups/:id", get(get_group_status_handler))
+        .route_layer(middleware::from_fn_with_state(
+            relayer_state.clone(),
+            require_api_key,
+        ));
+
+    // Route for streaming the status of a specific request as server-sent events, which also
+    // accepts a stream token in the query as `EventSource` cannot send an API key
+    let stream_routes = Router::new()
+        .route("/api/status/:id/stream", get(get_status_stream_handler))
+        .route_layer(middleware::from_fn_with_state(
+            relayer_state.clone(),
+            require_api_key_or_stream_token,
+        ));
+
+    // Routes for the IMAP service
+    let email_routes = Router::new()
+        // Route for receiving emails
+        .route("/api/receiveEmail", post(receive_email_handler))
+        .route_layer(middleware::from_fn_with_state(
+            relayer_state.clone(),
+            require_signature,
+        ));
+
+    // Routes for administrators
+    let admin_routes = Router::new()
+        // Routes for issuing and listing API keys
+        .route(
+            "/api/admin/apiKeys",
+            get(get_api_keys_handler).post(create_api_key_handler),
+        )
+        // Route for revoking an API key
+        .route("/api/admin/apiKeys/:id", delete(revoke_api_key_handler))
+        // Route for listing the DKIM keys revoked by their domains
+        .route(
+            "/api/admin/dkimKeys/revoked",
+            get(get_revoked_dkim_keys_handler),
+        )
+        // Route for listing the state of the prover services
+        .route("/api/admin/provers", get(get_provers_handler))
+        .route_layer(middleware::from_fn_with_state(
+            relayer_state.clone(),
+            require_admin_token,
+        ));
+
     Router::new()
         // Route for health check endpoint
         .route("/api/healthz", get(health_checker_handler))
-        // Route for submitting email transaction authentication requests
-        .route("/api/submit", post(submit_handler))
         // Route for computing the account salt
         .route("/api/accountSalt", post(account_salt_handler))
-        // Route for receiving emails
-        .route("/api/receiveEmail", post(receive_email_handler))
-        // Route for retrieving the status of a specific request
-        .route("/api/status/:id", get(get_status_handler))
+        .merge(client_routes)
+        .merge(stream_routes)
+        .merge(email_routes)
+        .merge(admin_routes)
         // Attach the shared state to the router
         .with_state(relayer_state)
 }
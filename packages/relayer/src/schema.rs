@@ -10,6 +10,8 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "camelCase")]
 pub struct EmailTxAuthSchema {
     pub dkim_contract_address: Address,
+    /// The type of the DKIM registry at `dkim_contract_address`. Detected on-chain if unset.
+    pub dkim_registry_type: Option<DkimRegistryType>,
     /// The account code associated with the transaction.
     pub account_code: AccountCode,
     /// Indicates whether the code exists in the email.
@@ -26,6 +28,53 @@ pub struct EmailTxAuthSchema {
     pub body: String,
     /// The blockchain chain on which the transaction is to be executed.
     pub chain: String,
+    /// Optional on-chain submission of the generated `EmailAuthMsg`.
+    pub on_chain: Option<OnChainTxSchema>,
+    /// The number of seconds the request waits for a reply before it expires.
+    /// Defaults to the configured TTL.
+    pub ttl_secs: Option<u64>,
+    /// The URL the relayer posts status transitions of the request to.
+    /// Defaults to the configured webhook URL.
+    pub callback_url: Option<String>,
+    /// The name of the circuit profile the reply is proven with.
+    /// Defaults to the `default` profile.
+    pub circuit_profile: Option<String>,
+    /// Indicates whether the proof commits to the recipient email address in the command.
+    /// Not supported with `on_chain`.
+    #[serde(default)]
+    pub recipient_bound: bool,
+}
+
+/// The DKIM registry contracts the relayer can register public key hashes in.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DkimRegistryType {
+    /// `UserOverrideableDKIMRegistry`, authorized by its main authorizer.
+    UserOverridable,
+    /// `ECDSAOwnedDKIMRegistry`, authorized by its ECDSA signer.
+    EcdsaOwned,
+}
+
+/// Describes the transaction the relayer sends once the `EmailAuthMsg` is generated.
+///
+/// `EmailAuth.authEmail` can only be called by the controller of the `EmailAuth` contract,
+/// so the transaction is either sent to a controller contract that forwards the message,
+/// or directly to `EmailAuth` when the relayer's wallet is the controller.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OnChainTxSchema {
+    /// The address of the contract the transaction is sent to.
+    pub target_address: Address,
+    /// The signature of the controller function to call, e.g.
+    /// `handleRecovery((uint256,bytes[],uint256,(string,bytes32,uint256,string,bytes32,bytes32,bool,bytes)),uint256)`.
+    /// If `None`, `authEmail` is called on the target address.
+    pub function_signature: Option<String>,
+    /// The position of the `EmailAuthMsg` argument in the function parameters.
+    #[serde(default)]
+    pub email_auth_msg_index: usize,
+    /// The remaining function arguments in order, parsed according to their parameter types.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -34,3 +83,20 @@ pub struct AccountSaltSchema {
     pub account_code: String,
     pub email_address: String,
 }
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiKeySchema {
+    /// The name of the client the API key is issued to.
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SubmitBatchSchema {
+    /// The requests of the group, one per recipient.
+    pub requests: Vec<EmailTxAuthSchema>,
+    /// The number of proven replies after which the group is complete.
+    /// Defaults to the number of requests.
+    pub threshold: Option<usize>,
+}
@@ -0,0 +1,215 @@
+//! Email transports used to send the relayer's emails.
+//!
+//! The transport is selected with `emailTransport` in the config:
+//! - `http` posts to the relayer-smtp service at `smtpUrl` (the default).
+//! - `smtp` talks to an SMTP server directly.
+//! - `file` writes every email to a directory instead of sending it, for tests.
+
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, EmailTransportConfig, SmtpConfig, SmtpTls},
+    mail::EmailMessage,
+};
+
+/// A transport that delivers emails.
+#[async_trait]
+pub trait EmailSender: Send + Sync + std::fmt::Debug {
+    /// Sends an email.
+    ///
+    /// # Arguments
+    ///
+    /// * `email` - The `EmailMessage` to be sent.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the Message-ID of the sent email, which replies refer to.
+    async fn send(&self, email: &EmailMessage) -> Result<String>;
+}
+
+/// Creates the email transport selected in the config.
+///
+/// # Arguments
+///
+/// * `config` - The configuration of the relayer.
+/// * `http_client` - The HTTP client used by the `http` transport.
+///
+/// # Returns
+///
+/// A `Result` containing the email transport.
+pub fn create_email_sender(config: &Config, http_client: Client) -> Result<Arc<dyn EmailSender>> {
+    let sender: Arc<dyn EmailSender> = match &config.email_transport {
+        EmailTransportConfig::Http => Arc::new(HttpEmailSender {
+            http_client,
+            smtp_url: config.smtp_url.clone(),
+        }),
+        EmailTransportConfig::Smtp(smtp_config) => Arc::new(SmtpEmailSender::new(smtp_config)?),
+        EmailTransportConfig::File { dir } => Arc::new(FileEmailSender {
+            dir: PathBuf::from(dir),
+        }),
+    };
+    Ok(sender)
+}
+
+/// Represents the response from the email server after sending an email.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EmailResponse {
+    status: String,
+    message_id: String,
+}
+
+/// Sends emails through the relayer-smtp service.
+#[derive(Debug)]
+pub struct HttpEmailSender {
+    http_client: Client,
+    smtp_url: String,
+}
+
+#[async_trait]
+impl EmailSender for HttpEmailSender {
+    async fn send(&self, email: &EmailMessage) -> Result<String> {
+        // Send POST request to email server
+        let response = self
+            .http_client
+            .post(format!("{}/api/sendEmail", self.smtp_url))
+            .json(email)
+            .send()
+            .await?;
+
+        // Check if the email was sent successfully
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "Failed to send email: {}",
+                response.text().await.unwrap_or_default()
+            ));
+        }
+
+        let response_body: EmailResponse = response
+            .json()
+            .await
+            .context("Failed to parse email server response")?;
+        Ok(response_body.message_id)
+    }
+}
+
+/// Sends emails directly to an SMTP server.
+#[derive(Debug)]
+pub struct SmtpEmailSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpEmailSender {
+    /// Creates an SMTP transport from its configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `smtp_config` - The configuration of the SMTP server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SmtpEmailSender`.
+    pub fn new(smtp_config: &SmtpConfig) -> Result<Self> {
+        let builder = match smtp_config.tls {
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp_config.host)?
+            }
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp_config.host)?,
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp_config.host)
+            }
+        };
+
+        let mut builder = builder.port(smtp_config.port);
+        if let Some(username) = &smtp_config.username {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                smtp_config.password.clone().unwrap_or_default(),
+            ));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from: smtp_config.from.parse().context("Invalid sender address")?,
+        })
+    }
+
+    /// Generates a unique Message-ID in the domain of the sender address.
+    fn generate_message_id(&self) -> String {
+        format!("<{}@{}>", Uuid::new_v4(), self.from.email.domain())
+    }
+}
+
+#[async_trait]
+impl EmailSender for SmtpEmailSender {
+    async fn send(&self, email: &EmailMessage) -> Result<String> {
+        let message_id = self.generate_message_id();
+
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .to(email.to.parse().context("Invalid recipient address")?)
+            .subject(&email.subject)
+            .message_id(Some(message_id.clone()));
+        if let Some(reply_to) = &email.reply_to {
+            builder = builder.in_reply_to(reply_to.clone());
+        }
+        if let Some(reference) = &email.reference {
+            builder = builder.references(reference.clone());
+        }
+
+        let alternative =
+            MultiPart::alternative_plain_html(email.body_plain.clone(), email.body_html.clone());
+        let body = match &email.body_attachments {
+            Some(attachments) if !attachments.is_empty() => {
+                let mut related = MultiPart::related().multipart(alternative);
+                for attachment in attachments {
+                    related = related.singlepart(
+                        Attachment::new_inline(attachment.inline_id.clone()).body(
+                            attachment.contents.clone(),
+                            ContentType::parse(&attachment.content_type)?,
+                        ),
+                    );
+                }
+                related
+            }
+            _ => alternative,
+        };
+
+        self.transport.send(builder.multipart(body)?).await?;
+        Ok(message_id)
+    }
+}
+
+/// Writes emails to a directory instead of sending them.
+#[derive(Debug)]
+pub struct FileEmailSender {
+    dir: PathBuf,
+}
+
+#[async_trait]
+impl EmailSender for FileEmailSender {
+    async fn send(&self, email: &EmailMessage) -> Result<String> {
+        let id = Uuid::new_v4();
+        let message_id = format!("<{}@localhost>", id);
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let content = serde_json::to_vec_pretty(&serde_json::json!({
+            "messageId": message_id,
+            "email": email,
+        }))?;
+        tokio::fs::write(self.dir.join(format!("{}.json", id)), content).await?;
+
+        Ok(message_id)
+    }
+}
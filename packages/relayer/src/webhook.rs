@@ -0,0 +1,263 @@
+//! Webhook callbacks on request status transitions.
+//!
+//! Every transition of a request with a callback URL enqueues a `DeliverWebhook` job, so
+//! callbacks are retried with the queue's backoff and survive restarts. Each delivery attempt is
+//! recorded in the `webhook_deliveries` table.
+//!
+//! Callback URLs supplied by clients must resolve to public addresses, unless their host is listed
+//! in `webhook.allowedHosts`. The check is repeated on every delivery and the connection is pinned
+//! to the checked address, so a DNS change cannot redirect a callback to an internal service.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use axum::http::header::CONTENT_TYPE;
+use relayer_utils::LOG;
+use reqwest::{redirect::Policy, Client, Url};
+use serde_json::json;
+use slog::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    auth::{sign_payload, SIGNATURE_HEADER},
+    config::WebhookConfig,
+    model::{
+        get_email_auth_msg, get_on_chain_transaction, get_request, insert_webhook_delivery,
+        JobModel, RequestStatus,
+    },
+    queue::{enqueue, Job},
+    RelayerState,
+};
+
+/// Enqueues a callback reporting a status transition of a request.
+///
+/// Callbacks are best effort, so failures are logged instead of failing the transition.
+///
+/// # Arguments
+///
+/// * `relayer_state` - The current state of the relayer.
+/// * `request_id` - The unique identifier of the request.
+/// * `status` - The status the request transitioned to.
+pub async fn notify_status(relayer_state: &RelayerState, request_id: Uuid, status: RequestStatus) {
+    let request = match get_request(&relayer_state.db, request_id).await {
+        Ok(request) => request,
+        Err(e) => {
+            error!(LOG, "Failed to get request {}: {:?}", request_id, e);
+            return;
+        }
+    };
+
+    let Some(url) = request
+        .email_tx_auth
+        .callback_url
+        .or_else(|| relayer_state.config.webhook.default_url.clone())
+        .filter(|url| !url.is_empty())
+    else {
+        return;
+    };
+
+    if let Err(e) = enqueue(
+        relayer_state,
+        request_id,
+        Job::DeliverWebhook { url, status },
+    )
+    .await
+    {
+        error!(
+            LOG,
+            "Failed to enqueue webhook for request {}: {:?}", request_id, e
+        );
+    }
+}
+
+/// Posts a status transition of a request to its callback URL and records the attempt.
+///
+/// The payload is signed with the configured secret in the `X-Relayer-Signature` header.
+///
+/// # Arguments
+///
+/// * `job` - The job delivering the callback.
+/// * `url` - The callback URL.
+/// * `status` - The status the request transitioned to.
+/// * `relayer_state` - The current state of the relayer.
+///
+/// # Returns
+///
+/// A `Result` indicating whether the callback URL accepted the payload.
+pub async fn deliver_webhook(
+    job: &JobModel,
+    url: &str,
+    status: RequestStatus,
+    relayer_state: &RelayerState,
+) -> Result<()> {
+    let request = get_request(&relayer_state.db, job.request_id).await?;
+    let email_auth_msg = get_email_auth_msg(&relayer_state.db, job.request_id).await?;
+    let transaction = get_on_chain_transaction(&relayer_state.db, job.request_id).await?;
+
+    let payload = serde_json::to_vec(&json!({
+        "requestId": job.request_id,
+        "status": status,
+        "errorCode": request.error_code,
+        "errorMessage": request.error_message,
+        "emailAuthMsg": email_auth_msg,
+        "transaction": transaction,
+        "timestamp": chrono::Utc::now().timestamp(),
+    }))?;
+
+    let webhook_config = &relayer_state.config.webhook;
+    let http_client = if webhook_config.default_url.as_deref() == Some(url) {
+        relayer_state.http_client.clone()
+    } else {
+        match check_callback_url(webhook_config, url).await {
+            Ok(None) => relayer_state.http_client.clone(),
+            Ok(Some((host, addr))) => Client::builder()
+                .resolve(&host, addr)
+                .redirect(Policy::none())
+                .build()?,
+            Err(e) => {
+                let error = format!("Callback URL is not allowed: {}", e);
+                insert_webhook_delivery(
+                    &relayer_state.db,
+                    job,
+                    url,
+                    status,
+                    None,
+                    Some(error.clone()),
+                )
+                .await?;
+                return Err(anyhow!(error));
+            }
+        }
+    };
+    let mut http_request = http_client
+        .post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .timeout(Duration::from_secs(webhook_config.timeout_secs));
+    if let Some(secret) = webhook_config.secret.as_deref().filter(|s| !s.is_empty()) {
+        http_request = http_request.header(SIGNATURE_HEADER, sign_payload(secret, &payload));
+    }
+
+    let (response_status, result) = match http_request.body(payload).send().await {
+        Ok(response) if response.status().is_success() => (Some(response.status()), Ok(())),
+        Ok(response) => (
+            Some(response.status()),
+            Err(anyhow!("Callback URL responded with {}", response.status())),
+        ),
+        Err(e) => (e.status(), Err(anyhow!("Failed to post callback: {}", e))),
+    };
+
+    insert_webhook_delivery(
+        &relayer_state.db,
+        job,
+        url,
+        status,
+        response_status.map(|status| status.as_u16() as i32),
+        result.as_ref().err().map(|e| e.to_string()),
+    )
+    .await?;
+
+    if result.is_ok() {
+        info!(
+            LOG,
+            "Webhook for request {} ({}) delivered", job.request_id, status
+        );
+    }
+    result
+}
+
+/// Checks that the relayer may post callbacks to a URL.
+///
+/// The URL must use http(s). Unless its host is listed in `webhook.allowedHosts`, every address
+/// the host resolves to must be public, which excludes loopback, private, link-local (including
+/// cloud metadata services) and other special-purpose addresses.
+///
+/// # Arguments
+///
+/// * `config` - The webhook configuration.
+/// * `url` - The callback URL.
+///
+/// # Returns
+///
+/// A `Result` containing the host and the checked address to connect to, or `None` if the host
+/// is allowlisted.
+pub async fn check_callback_url(
+    config: &WebhookConfig,
+    url: &str,
+) -> Result<Option<(String, SocketAddr)>> {
+    let url = Url::parse(url)?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(anyhow!("URL must use http or https"));
+    }
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("URL has no host"))?
+        .to_lowercase();
+    if config
+        .allowed_hosts
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&host))
+    {
+        return Ok(None);
+    }
+    if !config.allowed_hosts.is_empty() {
+        return Err(anyhow!("Host {} is not allowed", host));
+    }
+
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("URL has no port"))?;
+    // IPv6 literals are bracketed in URLs
+    let lookup_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup_host, port))
+        .await
+        .map_err(|e| anyhow!("Failed to resolve {}: {}", host, e))?
+        .collect();
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(anyhow!(
+            "Host {} resolves to non-public address {}",
+            host,
+            addr.ip()
+        ));
+    }
+    let addr = addrs
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Host {} has no address", host))?;
+
+    Ok(Some((lookup_host.to_string(), addr)))
+}
+
+/// Checks whether an address is reachable on the public internet.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // "This network", shared address space and reserved ranges
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first_segment = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local and link-local addresses
+                || (first_segment & 0xfe00) == 0xfc00
+                || (first_segment & 0xffc0) == 0xfe80)
+        }
+    }
+}